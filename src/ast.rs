@@ -1,5 +1,7 @@
 use std::fmt::Display;
 
+use crate::span::Span;
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Program {
     pub block: Block,
@@ -11,9 +13,10 @@ impl Display for Program {
     }
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Block {
     pub statements: Vec<Statement>,
+    pub span: Span,
 }
 
 impl Display for Block {
@@ -31,18 +34,58 @@ impl Display for Block {
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Statement {
     LetBe(LetBe),
+    LetBeArray(LetBeArray),
     SetTo(SetTo),
+    SetIndexTo(SetIndexTo),
     Rep(Rep),
+    While(While),
+    Break(Span),
+    Continue(Span),
+    Function(Function),
+    Return(Return),
     Print(Print),
+    Read(Read),
+    Eat(Eat),
+    Error(Span),
+}
+
+impl Statement {
+    pub fn span(&self) -> Span {
+        match self {
+            Statement::LetBe(let_be) => let_be.span,
+            Statement::LetBeArray(let_be_array) => let_be_array.span,
+            Statement::SetTo(set_to) => set_to.span,
+            Statement::SetIndexTo(set_index_to) => set_index_to.span,
+            Statement::Rep(rep) => rep.span,
+            Statement::While(while_) => while_.span,
+            Statement::Break(span) | Statement::Continue(span) => *span,
+            Statement::Function(function) => function.span,
+            Statement::Return(return_) => return_.span,
+            Statement::Print(print) => print.span,
+            Statement::Read(read) => read.span,
+            Statement::Eat(eat) => eat.span,
+            Statement::Error(span) => *span,
+        }
+    }
 }
 
 impl Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Statement::LetBe(let_be) => write!(f, "statement: {}", let_be),
+            Statement::LetBeArray(let_be_array) => write!(f, "statement: {}", let_be_array),
             Statement::SetTo(set_to) => write!(f, "statement: {}", set_to),
+            Statement::SetIndexTo(set_index_to) => write!(f, "statement: {}", set_index_to),
             Statement::Rep(rep) => write!(f, "statement: {}", rep),
+            Statement::While(while_) => write!(f, "statement: {}", while_),
+            Statement::Break(_) => write!(f, "statement: break"),
+            Statement::Continue(_) => write!(f, "statement: continue"),
+            Statement::Function(function) => write!(f, "statement: {}", function),
+            Statement::Return(return_) => write!(f, "statement: {}", return_),
             Statement::Print(print) => write!(f, "statement: {}", print),
+            Statement::Read(read) => write!(f, "statement: {}", read),
+            Statement::Eat(eat) => write!(f, "statement: {}", eat),
+            Statement::Error(_) => write!(f, "statement: error"),
         }
     }
 }
@@ -51,6 +94,7 @@ impl Display for Statement {
 pub struct LetBe {
     pub variable: String,
     pub expr: Expr,
+    pub span: Span,
 }
 
 impl Display for LetBe {
@@ -59,10 +103,24 @@ impl Display for LetBe {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct LetBeArray {
+    pub variable: String,
+    pub size: i32,
+    pub span: Span,
+}
+
+impl Display for LetBeArray {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "let:\n- {}\n- be \n- [{}]", self.variable, self.size)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct SetTo {
     pub variable: String,
     pub expr: Expr,
+    pub span: Span,
 }
 
 impl Display for SetTo {
@@ -71,41 +129,161 @@ impl Display for SetTo {
     }
 }
 
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SetIndexTo {
+    pub variable: String,
+    pub index: Expr,
+    pub expr: Expr,
+    pub span: Span,
+}
+
+impl Display for SetIndexTo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "set:\n- {}\n- [{}]\n- to \n- {}", self.variable, self.index, self.expr)
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Rep {
     pub expr: Expr,
+    pub counter: Option<String>,
     pub block: Block,
+    pub else_block: Option<Block>,
+    pub span: Span,
 }
 
 impl Display for Rep {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "rep:\n- {}\n- {{\n- {}\n- }}", self.expr, self.block)
+        write!(f, "rep:\n- {}", self.expr)?;
+
+        if let Some(counter) = &self.counter {
+            write!(f, "\n- as\n- {}", counter)?;
+        }
+
+        write!(f, "\n- {{\n- {}\n- }}", self.block)?;
+
+        if let Some(else_block) = &self.else_block {
+            write!(f, "\n- else\n- {{\n- {}\n- }}", else_block)?;
+        }
+
+        Ok(())
     }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Print {
+pub struct While {
+    pub expr: Expr,
+    pub block: Block,
+    pub span: Span,
+}
+
+impl Display for While {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "while:\n- {}\n- {{\n- {}\n- }}", self.expr, self.block)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Function {
+    pub name: String,
+    pub parameters: Vec<String>,
+    pub block: Block,
+    pub span: Span,
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "fn:\n- {}\n- ({})\n- {{\n- {}\n- }}", self.name, self.parameters.join(", "), self.block)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Return {
     pub expr: Expr,
+    pub span: Span,
+}
+
+impl Display for Return {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "return:\n- {}", self.expr)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Print {
+    pub items: Vec<PrintItem>,
+    pub span: Span,
 }
 
 impl Display for Print {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "print({})", self.expr)
+        let items: Vec<String> = self.items.iter().map(|item| item.to_string()).collect();
+
+        write!(f, "print({})", items.join(", "))
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Read {
+    pub variable: String,
+    pub span: Span,
+}
+
+impl Display for Read {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "read:\n- {}", self.variable)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Eat {
+    pub variable: String,
+    pub span: Span,
+}
+
+impl Display for Eat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "eat:\n- {}", self.variable)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PrintItem {
+    Str(String, Span),
+    Expr(Box<Expr>),
+}
+
+impl Display for PrintItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PrintItem::Str(s, _) => write!(f, "string: {:?}", s),
+            PrintItem::Expr(expr) => write!(f, "{}", expr),
+        }
     }
 }
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Expr {
-    And(Box<Expr>, Sent),
-    Or(Box<Expr>, Sent),
+    And(Box<Expr>, Sent, Span),
+    Or(Box<Expr>, Sent, Span),
     Sent(Sent),
 }
 
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::And(_, _, span) |
+            Expr::Or(_, _, span) => *span,
+            Expr::Sent(sent) => sent.span(),
+        }
+    }
+}
+
 impl Display for Expr {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Expr::And(expr, sent) => write!(f, "expr:\n- {}\n- & \n- {}", expr, sent),
-            Expr::Or(expr, sent) => write!(f, "expr:\n- {}\n- | \n- {}", expr, sent),
+            Expr::And(expr, sent, _) => write!(f, "expr:\n- {}\n- & \n- {}", expr, sent),
+            Expr::Or(expr, sent, _) => write!(f, "expr:\n- {}\n- | \n- {}", expr, sent),
             Expr::Sent(sent) => write!(f, "expr:\n- {}", sent),
         }
     }
@@ -113,18 +291,38 @@ impl Display for Expr {
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Sent {
-    Equals(Box<Sent>, Comp),
-    Greater(Box<Sent>, Comp),
-    Less(Box<Sent>, Comp),
+    Equals(Box<Sent>, Comp, Span),
+    Greater(Box<Sent>, Comp, Span),
+    Less(Box<Sent>, Comp, Span),
+    NotEquals(Box<Sent>, Comp, Span),
+    GreaterEquals(Box<Sent>, Comp, Span),
+    LessEquals(Box<Sent>, Comp, Span),
     Comp(Comp),
 }
 
+impl Sent {
+    pub fn span(&self) -> Span {
+        match self {
+            Sent::Equals(_, _, span) |
+            Sent::Greater(_, _, span) |
+            Sent::Less(_, _, span) |
+            Sent::NotEquals(_, _, span) |
+            Sent::GreaterEquals(_, _, span) |
+            Sent::LessEquals(_, _, span) => *span,
+            Sent::Comp(comp) => comp.span(),
+        }
+    }
+}
+
 impl Display for Sent {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Sent::Equals(sent, comp) => write!(f, "sent:\n- {}\n- = \n- {}", sent, comp),
-            Sent::Greater(sent, comp) => write!(f, "sent:\n- {}\n- > \n- {}", sent, comp),
-            Sent::Less(sent, comp) => write!(f, "sent:\n- {}\n- < \n- {}", sent, comp),
+            Sent::Equals(sent, comp, _) => write!(f, "sent:\n- {}\n- = \n- {}", sent, comp),
+            Sent::Greater(sent, comp, _) => write!(f, "sent:\n- {}\n- > \n- {}", sent, comp),
+            Sent::Less(sent, comp, _) => write!(f, "sent:\n- {}\n- < \n- {}", sent, comp),
+            Sent::NotEquals(sent, comp, _) => write!(f, "sent:\n- {}\n- != \n- {}", sent, comp),
+            Sent::GreaterEquals(sent, comp, _) => write!(f, "sent:\n- {}\n- >= \n- {}", sent, comp),
+            Sent::LessEquals(sent, comp, _) => write!(f, "sent:\n- {}\n- <= \n- {}", sent, comp),
             Sent::Comp(comp) => write!(f, "sent:\n- {}", comp),
         }
     }
@@ -132,16 +330,32 @@ impl Display for Sent {
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Comp {
-    Add(Box<Comp>, Term),
-    Sub(Box<Comp>, Term),
+    Add(Box<Comp>, Term, Span),
+    Sub(Box<Comp>, Term, Span),
+    BitOr(Box<Comp>, Term, Span),
+    Xor(Box<Comp>, Term, Span),
     Term(Term),
 }
 
+impl Comp {
+    pub fn span(&self) -> Span {
+        match self {
+            Comp::Add(_, _, span) |
+            Comp::Sub(_, _, span) |
+            Comp::BitOr(_, _, span) |
+            Comp::Xor(_, _, span) => *span,
+            Comp::Term(term) => term.span(),
+        }
+    }
+}
+
 impl Display for Comp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Comp::Add(comp, term) => write!(f, "comp:\n- {}\n- + \n- {}", comp, term),
-            Comp::Sub(comp, term) => write!(f, "comp:\n- {}\n- - \n- {}", comp, term),
+            Comp::Add(comp, term, _) => write!(f, "comp:\n- {}\n- + \n- {}", comp, term),
+            Comp::Sub(comp, term, _) => write!(f, "comp:\n- {}\n- - \n- {}", comp, term),
+            Comp::BitOr(comp, term, _) => write!(f, "comp:\n- {}\n- .| \n- {}", comp, term),
+            Comp::Xor(comp, term, _) => write!(f, "comp:\n- {}\n- ^ \n- {}", comp, term),
             Comp::Term(term) => write!(f, "comp:\n- {}", term),
         }
     }
@@ -149,16 +363,38 @@ impl Display for Comp {
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Term {
-    Mul(Box<Term>, Fact),
-    Div(Box<Term>, Fact),
+    Mul(Box<Term>, Fact, Span),
+    Div(Box<Term>, Fact, Span),
+    Mod(Box<Term>, Fact, Span),
+    BitAnd(Box<Term>, Fact, Span),
+    Shl(Box<Term>, Fact, Span),
+    Shr(Box<Term>, Fact, Span),
     Fact(Fact),
 }
 
+impl Term {
+    pub fn span(&self) -> Span {
+        match self {
+            Term::Mul(_, _, span) |
+            Term::Div(_, _, span) |
+            Term::Mod(_, _, span) |
+            Term::BitAnd(_, _, span) |
+            Term::Shl(_, _, span) |
+            Term::Shr(_, _, span) => *span,
+            Term::Fact(fact) => fact.span(),
+        }
+    }
+}
+
 impl Display for Term {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Term::Mul(term, fact) => write!(f, "term:\n- {}\n- * \n- {}", term, fact),
-            Term::Div(term, fact) => write!(f, "term:\n- {}\n- / \n- {}", term, fact),
+            Term::Mul(term, fact, _) => write!(f, "term:\n- {}\n- * \n- {}", term, fact),
+            Term::Div(term, fact, _) => write!(f, "term:\n- {}\n- / \n- {}", term, fact),
+            Term::Mod(term, fact, _) => write!(f, "term:\n- {}\n- % \n- {}", term, fact),
+            Term::BitAnd(term, fact, _) => write!(f, "term:\n- {}\n- .& \n- {}", term, fact),
+            Term::Shl(term, fact, _) => write!(f, "term:\n- {}\n- << \n- {}", term, fact),
+            Term::Shr(term, fact, _) => write!(f, "term:\n- {}\n- >> \n- {}", term, fact),
             Term::Fact(fact) => write!(f, "term:\n- {}", fact),
         }
     }
@@ -166,20 +402,35 @@ impl Display for Term {
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Fact {
-    Is(Prim),
-    Not(Prim),
-    Pos(Prim),
-    Neg(Prim),
+    Is(Box<Fact>, Span),
+    Not(Box<Fact>, Span),
+    Pos(Box<Fact>, Span),
+    Neg(Box<Fact>, Span),
+    BitNot(Box<Fact>, Span),
     Prim(Prim),
 }
 
+impl Fact {
+    pub fn span(&self) -> Span {
+        match self {
+            Fact::Is(_, span) |
+            Fact::Not(_, span) |
+            Fact::Pos(_, span) |
+            Fact::Neg(_, span) |
+            Fact::BitNot(_, span) => *span,
+            Fact::Prim(prim) => prim.span(),
+        }
+    }
+}
+
 impl Display for Fact {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Fact::Is(p) => write!(f, "fact:\n- ?{}", p),
-            Fact::Not(p) => write!(f, "fact\n- !{}", p),
-            Fact::Pos(p) => write!(f, "fact\n- +{}", p),
-            Fact::Neg(p) => write!(f, "fact\n- -{}", p),
+            Fact::Is(p, _) => write!(f, "fact:\n- ?{}", p),
+            Fact::Not(p, _) => write!(f, "fact\n- !{}", p),
+            Fact::Pos(p, _) => write!(f, "fact\n- +{}", p),
+            Fact::Neg(p, _) => write!(f, "fact\n- -{}", p),
+            Fact::BitNot(p, _) => write!(f, "fact\n- ~{}", p),
             Fact::Prim(p) => write!(f, "fact\n- {}", p),
         }
     }
@@ -187,23 +438,48 @@ impl Display for Fact {
 
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
 pub enum Prim {
-    Expr(Box<Expr>),
-    Constant(i32),
-    Variable(String),
+    Expr(Box<Expr>, Span),
+    Constant(i32, Span),
+    Variable(String, Span),
+    Call(String, Vec<Expr>, Span),
+    Index(String, Box<Expr>, Span),
+}
+
+impl Prim {
+    pub fn span(&self) -> Span {
+        match self {
+            Prim::Expr(_, span) |
+            Prim::Constant(_, span) |
+            Prim::Variable(_, span) |
+            Prim::Call(_, _, span) |
+            Prim::Index(_, _, span) => *span,
+        }
+    }
 }
 
 impl Display for Prim {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Prim::Expr(e) => write!(f, "prim:\n- ({})", **e),
-            Prim::Constant(c) => write!(f, "prim:\n- constant: {}", c),
-            Prim::Variable(v) => write!(f, "prim:\n- variable: {}", v),
+            Prim::Expr(e, _) => write!(f, "prim:\n- ({})", **e),
+            Prim::Constant(c, _) => write!(f, "prim:\n- constant: {}", c),
+            Prim::Variable(v, _) => write!(f, "prim:\n- variable: {}", v),
+            Prim::Call(name, arguments, _) => {
+                write!(f, "prim:\n- call: {}", name)?;
+
+                for argument in arguments {
+                    write!(f, "\n- {}", argument)?;
+                }
+
+                Ok(())
+            },
+            Prim::Index(name, index, _) => write!(f, "prim:\n- index: {}\n- {}", name, index),
         }
     }
 }
 
 /* INTEGER PRIMITIVE */
 
+#[allow(dead_code)]
 #[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 pub struct Integer {
     pub value: i32,
@@ -1,94 +1,444 @@
-use crate::ast::*;
+use crate::{ast::*, mangle::{mangle, mangle_function}};
 
-pub fn transpile_program_to_c(program: &Program, ) -> String {
-    let c_program = transpile_block(&program.block);
+const RESERVED: &[&str] = &[
+    "auto", "break", "case", "char", "const", "continue", "default", "do", "double", "else", "enum", "extern",
+    "float", "for", "goto", "if", "inline", "int", "long", "register", "restrict", "return", "short", "signed",
+    "sizeof", "static", "struct", "switch", "typedef", "union", "unsigned", "void", "volatile", "while",
+    "bool", "true", "false", "alignas", "alignof", "nullptr", "static_assert", "thread_local", "typeof",
+    "EOF", "NULL", "BUFSIZ", "FILENAME_MAX", "FOPEN_MAX", "L_tmpnam", "L_ctermid", "P_tmpdir", "TMP_MAX",
+    "SEEK_SET", "SEEK_CUR", "SEEK_END", "stdin", "stdout", "stderr", "EXIT_SUCCESS", "EXIT_FAILURE", "RAND_MAX",
+    "MB_CUR_MAX", "unix", "linux", "i386",
+    "main", "abs", "sgn", "rem", "shift", "input", "bound", "alloc", "release", "printf",
+];
 
-    format!("#include <stdlib.h>\n#include <stdio.h>\n\nint sgn(int x) {{\n\treturn (x > 0) - (x < 0);\n}}\n\nint main() {c_program}")
+// Right shifts are written out so negative numbers keep their sign on any compiler.
+const SHIFT: &str = "\
+int shift(int x, long long y) {
+\tif (y >= 32) return 0;
+\tif (y <= -32) return -(x < 0);
+\tif (y >= 0) return (int)((unsigned)x << y);
+\treturn x < 0 ? ~(~x >> -y) : x >> -y;
+}";
+
+const INPUT: &str = "\
+int input(void) {
+\tlong long value = 0;
+\tint c, sign = 1, state = 0, valid = 1, empty = 1;
+\twhile ((c = getchar()) != EOF && c != '\\n') {
+\t\tempty = 0;
+\t\tif (c == ' ' || c == '\\t' || c == '\\r' || c == '\\f') { if (state == 1) valid = 0; if (state == 2) state = 3; }
+\t\telse if (c >= '0' && c <= '9' && state < 3) { state = 2; if (value <= 2147483648LL) value = value * 10 + (c - '0'); }
+\t\telse if ((c == '-' || c == '+') && state == 0) { state = 1; if (c == '-') sign = -1; }
+\t\telse valid = 0;
+\t}
+\tif (c == EOF && empty) { fflush(stdout); fprintf(stderr, \"runtime error: unexpected end of input\\n\"); exit(1); }
+\tvalue *= sign;
+\tif (!valid || state < 2 || value < -2147483648LL || value > 2147483647LL) { fflush(stdout); fprintf(stderr, \"runtime error: expected an integer\\n\"); exit(1); }
+\treturn (int)value;
+}";
+
+const BOUND: &str = "\
+int bound(int index, size_t length) {
+\tif (index < 0 || (size_t)index >= length) { fflush(stdout); fprintf(stderr, \"runtime error: index %i out of bounds for length %zu\\n\", index, length); exit(1); }
+\treturn index;
+}";
+
+// Arrays are taken from one zeroed allocation per call, which `release` frees as the function returns.
+const ALLOC: &str = "\
+int *alloc(size_t length) {
+\tint *arrays = calloc(length, sizeof *arrays);
+\tif (!arrays) { fflush(stdout); fprintf(stderr, \"runtime error: out of memory\\n\"); exit(1); }
+\treturn arrays;
+}
+
+int release(int *arrays, int value) {
+\tfree(arrays);
+\treturn value;
+}";
+
+pub fn transpile_program_to_c(program: &Program) -> String {
+    let mut frame = Frame::default();
+    let c_program = transpile_block(&program.block, &mut frame);
+
+    // Functions are declared up front, so they can call each other in any order.
+    let mut prototypes = String::new();
+    let mut functions = String::new();
+
+    for statement in &program.block.statements {
+        if let Statement::Function(function) = statement {
+            let signature = transpile_signature(function);
+            let has_arrays = function.block.statements.iter().any(|statement| matches!(statement, Statement::LetBeArray(_)));
+            let mut function_frame = Frame { is_releasing: has_arrays, ..Frame::default() };
+            let block = transpile_block(&function.block, &mut function_frame);
+
+            prototypes.push_str(&format!("{signature};\n\n"));
+            functions.push_str(&format!("{signature} {{ {}{block} {}}}\n\n", function_frame.declaration(), transpile_return("0", &function_frame)));
+        }
+    }
+
+    format!("#include <stdlib.h>\n#include <stdio.h>\n\nint sgn(int x) {{\n\treturn (x > 0) - (x < 0);\n}}\n\nint rem(int x, int y) {{\n\treturn y == -1 ? 0 : x % y;\n}}\n\n{SHIFT}\n\n{INPUT}\n\n{BOUND}\n\n{ALLOC}\n\n{prototypes}{functions}int main() {{ {}{c_program} }}", frame.declaration())
+}
+
+// C leaves evaluation order unspecified, so operands and arguments with effects go through `m__t` one at a time.
+#[derive(Default)]
+struct Frame {
+    next: usize,
+    count: usize,
+    arrays: usize,
+    // Set for functions with arrays, which free them whenever they return.
+    is_releasing: bool,
+}
+
+impl Frame {
+    fn take(&mut self) -> usize {
+        self.next += 1;
+        self.count = self.count.max(self.next);
+
+        self.next - 1
+    }
+
+    fn allocate(&mut self, size: i32) -> usize {
+        self.arrays += size as usize;
+
+        self.arrays - size as usize
+    }
+
+    fn declaration(&self) -> String {
+        let temps = match self.count {
+            0 => String::new(),
+            count => format!("int m__t[{count}]; "),
+        };
+
+        match self.arrays {
+            0 => temps,
+            arrays => format!("{temps}int *m__a = alloc({arrays}); "),
+        }
+    }
+}
+
+fn transpile_signature(function: &Function) -> String {
+    let parameters: Vec<String> = function.parameters.iter()
+        .map(|parameter| format!("int {}", mangle(parameter, RESERVED)))
+        .collect();
+
+    let parameters = match parameters.is_empty() {
+        true => "void".to_string(),
+        false => parameters.join(", "),
+    };
+
+    format!("int {}({parameters})", mangle_function(&function.name))
 }
 
-fn transpile_block(block: &Block) -> String {
+fn transpile_block(block: &Block, frame: &mut Frame) -> String {
     let mut c_block = String::new();
 
     for statement in &block.statements {
-        c_block.push_str(&transpile_statement(&statement));
+        c_block.push_str(&transpile_statement(statement, frame));
+    }
+
+    // Every `eat` opens a scope, closed here, so the name can be declared again inside it.
+    for _ in block.statements.iter().filter(|statement| matches!(statement, Statement::Eat(_))) {
+        c_block.push_str("} ");
     }
 
     format!("{{ {c_block} }}")
 }
 
-fn transpile_statement(statement: &Statement) -> String {
+fn transpile_statement(statement: &Statement, frame: &mut Frame) -> String {
     match statement {
         Statement::LetBe(let_be) => 
-            format!("int {} = {}; ", let_be.variable, transpile_expr(&let_be.expr)),
+            format!("int {} = {}; ", mangle(&let_be.variable, RESERVED), transpile_expr(&let_be.expr, frame)),
+        Statement::LetBeArray(let_be_array) => {
+            let offset = frame.allocate(let_be_array.size);
+
+            format!("int (*{})[{size}] = (int (*)[{size}])(m__a + {offset}); ", mangle(&let_be_array.variable, RESERVED), size = let_be_array.size)
+        },
         Statement::SetTo(set_to) => 
-            format!("{} = {}; ", set_to.variable, transpile_expr(&set_to.expr)),
-        Statement::Rep(rep) => 
-            format!("for (int i = 0; i < abs({}); ++i) {}", transpile_expr(&rep.expr), transpile_block(&rep.block)),
-        Statement::Print(print) => 
-            format!("printf(\"%i\", {}); ", transpile_expr(&print.expr)),
+            format!("{} = {}; ", mangle(&set_to.variable, RESERVED), transpile_expr(&set_to.expr, frame)),
+        Statement::SetIndexTo(set_index_to) => transpile_set_index_to(set_index_to, frame),
+        Statement::Rep(rep) => transpile_rep(rep, frame),
+        Statement::While(while_) =>
+            format!("while ({}) {} ", transpile_expr(&while_.expr, frame), transpile_block(&while_.block, frame)),
+        Statement::Break(_) => "break; ".to_string(),
+        // Functions are written out before main by transpile_program_to_c.
+        Statement::Function(_) => String::new(),
+        Statement::Return(return_) => {
+            let expr = transpile_expr(&return_.expr, frame);
+
+            transpile_return(&expr, frame)
+        },
+        Statement::Continue(_) => "continue; ".to_string(),
+        Statement::Print(print) => transpile_print(print, frame),
+        Statement::Read(read) => format!("{} = input(); ", mangle(&read.variable, RESERVED)),
+        Statement::Eat(_) => "{ ".to_string(),
+        Statement::Error(_) => unreachable!("programs with syntax errors are not transpiled"),
     }
 }
 
-fn transpile_expr(expr: &Expr) -> String {
+fn transpile_rep(rep: &Rep, frame: &mut Frame) -> String {
+    let counter = match &rep.counter {
+        Some(counter) => mangle(counter, RESERVED),
+        None => "m__i".into(),
+    };
+
+    let mut c_rep = format!("{{ int m__n = abs({}); for (int {counter} = 0; {counter} < m__n; ++{counter}) {} ", transpile_expr(&rep.expr, frame), transpile_block(&rep.block, frame));
+
+    if let Some(else_block) = &rep.else_block {
+        c_rep.push_str(&format!("if (m__n == 0) {} ", transpile_block(else_block, frame)));
+    }
+
+    c_rep.push_str("} ");
+
+    c_rep
+}
+
+fn transpile_return(value: &str, frame: &Frame) -> String {
+    match frame.is_releasing {
+        true => format!("return release(m__a, {value}); "),
+        false => format!("return {value}; "),
+    }
+}
+
+fn transpile_set_index_to(set_index_to: &SetIndexTo, frame: &mut Frame) -> String {
+    let array = mangle(&set_index_to.variable, RESERVED);
+
+    format!("{{ int m__k = {}; (*{array})[m__k] = {}; }} ", transpile_index(&array, &set_index_to.index, frame), transpile_expr(&set_index_to.expr, frame))
+}
+
+fn transpile_index(array: &str, index: &Expr, frame: &mut Frame) -> String {
+    format!("bound({}, sizeof *{array} / sizeof **{array})", transpile_expr(index, frame))
+}
+
+fn transpile_print(print: &Print, frame: &mut Frame) -> String {
+    let mut format = String::new();
+    let mut exprs = vec![];
+
+    for item in &print.items {
+        match item {
+            PrintItem::Str(string, _) => format.push_str(&escape_string(string).replace('%', "%%")),
+            PrintItem::Expr(expr) => {
+                format.push_str("%i");
+                exprs.push(&**expr);
+            },
+        }
+    }
+
+    let (assignments, arguments) = transpile_arguments(&exprs, frame);
+    let arguments: String = arguments.iter().map(|argument| format!(", {argument}")).collect();
+
+    format!("{assignments}printf(\"{format}\\n\"{arguments}); ")
+}
+
+fn transpile_arguments(exprs: &[&Expr], frame: &mut Frame) -> (String, Vec<String>) {
+    if exprs.iter().filter(|expr| expr_has_effects(expr)).count() < 2 {
+        return (String::new(), exprs.iter().map(|expr| transpile_expr(expr, frame)).collect());
+    }
+
+    let start = frame.next;
+    let mut assignments = String::new();
+    let mut arguments = vec![];
+
+    for expr in exprs {
+        if expr_has_effects(expr) {
+            let temp = frame.take();
+            assignments.push_str(&format!("m__t[{temp}] = {}, ", transpile_expr(expr, frame)));
+            arguments.push(format!("m__t[{temp}]"));
+        } else {
+            arguments.push(transpile_expr(expr, frame));
+        }
+    }
+
+    frame.next = start;
+
+    (assignments, arguments)
+}
+
+fn transpile_operands(is_sequenced: bool, frame: &mut Frame, left: impl FnOnce(&mut Frame) -> String, right: impl FnOnce(&mut Frame) -> String) -> (String, String, String) {
+    if !is_sequenced {
+        return (String::new(), left(frame), right(frame));
+    }
+
+    let temp = frame.take();
+    let assignment = format!("m__t[{temp}] = {}, ", left(frame));
+    let right = right(frame);
+    frame.next = temp;
+
+    (assignment, format!("m__t[{temp}]"), right)
+}
+
+// Octal escapes for anything outside printable ASCII, and `?` is escaped so no trigraphs are formed.
+fn escape_string(string: &str) -> String {
+    let mut escaped = String::new();
+
+    for byte in string.bytes() {
+        match byte {
+            b'"' => escaped.push_str("\\\""),
+            b'\\' => escaped.push_str("\\\\"),
+            b'?' => escaped.push_str("\\?"),
+            b'\n' => escaped.push_str("\\n"),
+            b'\t' => escaped.push_str("\\t"),
+            b' '..=b'~' => escaped.push(byte as char),
+            _ => escaped.push_str(&format!("\\{byte:03o}")),
+        }
+    }
+
+    escaped
+}
+
+fn transpile_expr(expr: &Expr, frame: &mut Frame) -> String {
+    // `&&` and `||` already evaluate their left operand first.
     match expr {
-        Expr::And(expr, sent) => 
-            format!("({} && {})", transpile_expr(&expr), transpile_sent(&sent)),
-        Expr::Or(expr, sent) =>
-            format!("({} || {})", transpile_expr(&expr), transpile_sent(&sent)),
+        Expr::And(expr, sent, _) => 
+            format!("({} && {})", transpile_expr(expr, frame), transpile_sent(sent, frame)),
+        Expr::Or(expr, sent, _) =>
+            format!("({} || {})", transpile_expr(expr, frame), transpile_sent(sent, frame)),
         Expr::Sent(sent) => 
-            format!("{}", transpile_sent(&sent)),
+            transpile_sent(sent, frame),
     }
 }
 
-fn transpile_sent(sent: &Sent) -> String {
+fn transpile_sent(sent: &Sent, frame: &mut Frame) -> String {
+    let (operator, sent, comp) = match sent {
+        Sent::Equals(sent, comp, _) => ("==", sent, comp),
+        Sent::Greater(sent, comp, _) => (">", sent, comp),
+        Sent::Less(sent, comp, _) => ("<", sent, comp),
+        Sent::NotEquals(sent, comp, _) => ("!=", sent, comp),
+        Sent::GreaterEquals(sent, comp, _) => (">=", sent, comp),
+        Sent::LessEquals(sent, comp, _) => ("<=", sent, comp),
+        Sent::Comp(comp) => return transpile_comp(comp, frame),
+    };
+
+    let is_sequenced = sent_has_effects(sent) && comp_has_effects(comp);
+    let (assignment, left, right) = transpile_operands(is_sequenced, frame, |frame| transpile_sent(sent, frame), |frame| transpile_comp(comp, frame));
+
+    format!("({assignment}{left} {operator} {right})")
+}
+
+fn transpile_comp(comp: &Comp, frame: &mut Frame) -> String {
+    let (operator, comp, term) = match comp {
+        Comp::Add(comp, term, _) => ("+", comp, term),
+        Comp::Sub(comp, term, _) => ("-", comp, term),
+        Comp::BitOr(comp, term, _) => ("|", comp, term),
+        Comp::Xor(comp, term, _) => ("^", comp, term),
+        Comp::Term(term) => return transpile_term(term, frame),
+    };
+
+    let is_sequenced = comp_has_effects(comp) && term_has_effects(term);
+    let (assignment, left, right) = transpile_operands(is_sequenced, frame, |frame| transpile_comp(comp, frame), |frame| transpile_term(term, frame));
+
+    format!("({assignment}{left} {operator} {right})")
+}
+
+fn transpile_term(term: &Term, frame: &mut Frame) -> String {
+    // What goes before, between and after the two operands.
+    let (term, fact, (before, between, after)) = match term {
+        Term::Mul(term, fact, _) => (term, fact, ("", " * ", "")),
+        Term::Div(term, fact, _) => (term, fact, ("", " / ", "")),
+        // C's % already truncates, rem only keeps INT_MIN % -1 from overflowing.
+        Term::Mod(term, fact, _) => (term, fact, ("rem(", ", ", ")")),
+        Term::BitAnd(term, fact, _) => (term, fact, ("", " & ", "")),
+        Term::Shl(term, fact, _) => (term, fact, ("shift(", ", ", ")")),
+        // Widened before negating, as -INT_MIN would overflow.
+        Term::Shr(term, fact, _) => (term, fact, ("shift(", ", -(long long)", ")")),
+        Term::Fact(fact) => return transpile_fact(fact, frame),
+    };
+
+    let is_sequenced = term_has_effects(term) && fact_has_effects(fact);
+    let (assignment, left, right) = transpile_operands(is_sequenced, frame, |frame| transpile_term(term, frame), |frame| transpile_fact(fact, frame));
+
+    format!("({assignment}{before}{left}{between}{right}{after})")
+}
+
+fn transpile_fact(fact: &Fact, frame: &mut Frame) -> String {
+    match fact {
+        // Every unary operator wraps its operand in parentheses, and negative constants are parenthesized too,
+        // so nested operators never form C's -- or ++.
+        Fact::Is(fact, _) => format!("sgn({})", transpile_fact(fact, frame)),
+        Fact::Not(fact, _) => format!("(!{})", transpile_fact(fact, frame)),
+        Fact::Pos(fact, _) => format!("(+{})", transpile_fact(fact, frame)),
+        Fact::Neg(fact, _) => format!("(-{})", transpile_fact(fact, frame)),
+        Fact::BitNot(fact, _) => format!("(~{})", transpile_fact(fact, frame)),
+        Fact::Prim(prim) => transpile_prim(prim, frame),
+    }
+}
+
+fn transpile_prim(prim: &Prim, frame: &mut Frame) -> String {
+    match prim {
+        Prim::Expr(expr, _) => format!("({})", transpile_expr(expr, frame)),
+        // i32::MIN has no literal in C, as 2147483648 does not fit in an int before it is negated.
+        Prim::Constant(i32::MIN, _) => "(-2147483647 - 1)".to_string(),
+        Prim::Constant(constant, _) if *constant < 0 => format!("({constant})"),
+        Prim::Constant(constant, _) => format!("{constant}"),
+        Prim::Variable(variable, _) => mangle(variable, RESERVED).into_owned(),
+        Prim::Call(function, arguments, _) => {
+            let arguments: Vec<&Expr> = arguments.iter().collect();
+            let (assignments, arguments) = transpile_arguments(&arguments, frame);
+            let call = format!("{}({})", mangle_function(function), arguments.join(", "));
+
+            match assignments.is_empty() {
+                true => call,
+                false => format!("({assignments}{call})"),
+            }
+        },
+        Prim::Index(array, index, _) => {
+            let array = mangle(array, RESERVED);
+
+            format!("(*{array})[{}]", transpile_index(&array, index, frame))
+        },
+    }
+}
+
+fn expr_has_effects(expr: &Expr) -> bool {
+    match expr {
+        Expr::And(expr, sent, _) |
+        Expr::Or(expr, sent, _) => expr_has_effects(expr) || sent_has_effects(sent),
+        Expr::Sent(sent) => sent_has_effects(sent),
+    }
+}
+
+fn sent_has_effects(sent: &Sent) -> bool {
     match sent {
-        Sent::Equals(sent, comp) => 
-            format!("({} == {})", transpile_sent(&sent), transpile_comp(&comp)),
-        Sent::Greater(sent, comp) => 
-            format!("({} > {})", transpile_sent(&sent), transpile_comp(&comp)),
-        Sent::Less(sent, comp) => 
-            format!("({} < {})", transpile_sent(&sent), transpile_comp(&comp)),
-        Sent::Comp(comp) =>
-            format!("{}", transpile_comp(&comp)),
+        Sent::Equals(sent, comp, _) |
+        Sent::Greater(sent, comp, _) |
+        Sent::Less(sent, comp, _) |
+        Sent::NotEquals(sent, comp, _) |
+        Sent::GreaterEquals(sent, comp, _) |
+        Sent::LessEquals(sent, comp, _) => sent_has_effects(sent) || comp_has_effects(comp),
+        Sent::Comp(comp) => comp_has_effects(comp),
     }
 }
 
-fn transpile_comp(comp: &Comp) -> String {
+fn comp_has_effects(comp: &Comp) -> bool {
     match comp {
-        Comp::Add(comp, term) => 
-            format!("({} + {})", transpile_comp(&comp), transpile_term(&term)),
-        Comp::Sub(comp, term) => 
-            format!("({} - {})", transpile_comp(&comp), transpile_term(&term)),
-        Comp::Term(term) =>
-            format!("{}", transpile_term(&term)),
+        Comp::Add(comp, term, _) |
+        Comp::Sub(comp, term, _) |
+        Comp::BitOr(comp, term, _) |
+        Comp::Xor(comp, term, _) => comp_has_effects(comp) || term_has_effects(term),
+        Comp::Term(term) => term_has_effects(term),
     }
 }
 
-fn transpile_term(term: &Term) -> String {
+fn term_has_effects(term: &Term) -> bool {
     match term {
-        Term::Mul(term, fact) =>
-        format!("({} * {})", transpile_term(&term), transpile_fact(&fact)),
-        Term::Div(term, fact) =>
-        format!("({} / {})", transpile_term(&term), transpile_fact(&fact)),
-        Term::Fact(fact) =>
-        format!("{}", transpile_fact(&fact)),
+        Term::Mul(term, fact, _) |
+        Term::Div(term, fact, _) |
+        Term::Mod(term, fact, _) |
+        Term::BitAnd(term, fact, _) |
+        Term::Shl(term, fact, _) |
+        Term::Shr(term, fact, _) => term_has_effects(term) || fact_has_effects(fact),
+        Term::Fact(fact) => fact_has_effects(fact),
     }
 }
 
-fn transpile_fact(fact: &Fact) -> String {
+fn fact_has_effects(fact: &Fact) -> bool {
     match fact {
-        Fact::Is(prim) => format!("sgn({})", transpile_prim(prim)),
-        Fact::Not(prim) => format!("(!{})", transpile_prim(prim)),
-        Fact::Pos(prim) => format!("(+{})", transpile_prim(prim)),
-        Fact::Neg(prim) => format!("(-{})", transpile_prim(prim)),
-        Fact::Prim(prim) => format!("{}", transpile_prim(prim)),
+        Fact::Is(fact, _) |
+        Fact::Not(fact, _) |
+        Fact::Pos(fact, _) |
+        Fact::Neg(fact, _) |
+        Fact::BitNot(fact, _) => fact_has_effects(fact),
+        Fact::Prim(Prim::Expr(expr, _)) => expr_has_effects(expr),
+        Fact::Prim(Prim::Constant(..) | Prim::Variable(..)) => false,
+        Fact::Prim(Prim::Call(..) | Prim::Index(..)) => true,
     }
 }
-
-fn transpile_prim(prim: &Prim) -> String {
-    match prim {
-        Prim::Expr(expr) => format!("({})", transpile_expr(&expr)),
-        Prim::Constant(constant) => format!("{constant}"),
-        Prim::Variable(variable) => format!("{variable}"),
-    }
-}
\ No newline at end of file
@@ -1,82 +1,418 @@
-use std::iter::from_fn;
+use std::{iter::Peekable, str::CharIndices, fmt::Display, borrow::Cow};
 
-use crate::token::Token;
+use crate::{token::Token, span::{Span, Spanned}};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum LexerError{
-    InvalidInteger,
-    InvalidCharacter(String),
-}
-
-pub fn tokenize_program(data: &str) -> Result<Vec<Token>, LexerError> {
-    let mut tokens = vec![];
-    let mut reader = data.chars().peekable();
-
-    while let Some(c) = reader.next() {
-        let token = match c {
-            c if c.is_whitespace() => continue,
-            // Comments
-            '#' => {
-                loop {
-                    if let None = reader.next_if(|n| *n != '\n') {
-                        break;
+    MalformedInteger(String, Span),
+    IntegerTooLarge(String, Span),
+    InvalidCharacter(String, Span),
+    UnterminatedComment(Span),
+    UnterminatedString(Span),
+    InvalidEscape(String, Span),
+}
+
+impl Display for LexerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output: Cow<str> = match self {
+            LexerError::MalformedInteger(literal, span) =>
+                Cow::Owned(format!("{span}: malformed integer: {literal}")),
+            LexerError::IntegerTooLarge(literal, span) =>
+                Cow::Owned(format!("{span}: integer too large: {literal}, allowed range is {}", integer_range(literal))),
+            LexerError::InvalidCharacter(character, span) =>
+                Cow::Owned(format!("{span}: invalid character: {character}")),
+            LexerError::UnterminatedComment(span) =>
+                Cow::Owned(format!("{span}: unterminated block comment")),
+            LexerError::UnterminatedString(span) =>
+                Cow::Owned(format!("{span}: unterminated string")),
+            LexerError::InvalidEscape(escape, span) =>
+                Cow::Owned(format!("{span}: invalid escape sequence: {escape}")),
+        };
+
+        write!(f, "{output}")
+    }
+}
+
+impl std::error::Error for LexerError {}
+
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+struct Reader<'a> {
+    chars: Peekable<CharIndices<'a>>,
+    position: Position,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a str) -> Self {
+        Reader { chars: data.char_indices().peekable(), position: Position { offset: 0, line: 1, column: 1 } }
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let (offset, c) = self.chars.next()?;
+
+        self.position.offset = offset + c.len_utf8();
+
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+
+        Some(c)
+    }
+
+    fn next_if(&mut self, func: impl FnOnce(&char) -> bool) -> Option<char> {
+        match self.chars.peek() {
+            Some((_, c)) if func(c) => self.next(),
+            _ => None,
+        }
+    }
+
+    fn span_from(&self, start: Position) -> Span {
+        Span { start: start.offset, end: self.position.offset, line: start.line, column: start.column }
+    }
+}
+
+#[cfg(feature = "unicode")]
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || unicode_xid::UnicodeXID::is_xid_start(c)
+}
+
+#[cfg(feature = "unicode")]
+fn is_identifier_continue(c: char) -> bool {
+    unicode_xid::UnicodeXID::is_xid_continue(c)
+}
+
+#[cfg(not(feature = "unicode"))]
+fn is_identifier_start(c: char) -> bool {
+    c == '_' || c.is_ascii_alphabetic()
+}
+
+#[cfg(not(feature = "unicode"))]
+fn is_identifier_continue(c: char) -> bool {
+    c == '_' || c.is_ascii_alphanumeric()
+}
+
+fn split_radix(literal: &str) -> (&str, u32) {
+    match literal.get(..2) {
+        Some("0x") => (&literal[2..], 16),
+        Some("0b") => (&literal[2..], 2),
+        Some("0o") => (&literal[2..], 8),
+        _ => (literal, 10),
+    }
+}
+
+fn integer_range(literal: &str) -> &'static str {
+    match split_radix(literal).1 {
+        16 => "0x0 to 0xffff_ffff",
+        2  => "0b0 to 0b1111_1111_1111_1111_1111_1111_1111_1111",
+        8  => "0o0 to 0o37_777_777_777",
+        _  => "-2_147_483_648 to 2_147_483_647",
+    }
+}
+
+// Decimal literals may be 2147483648, which only fits once the parser negates it.
+fn parse_integer(literal: &str, span: Span) -> Result<i64, LexerError> {
+    let (digits, radix) = split_radix(literal);
+
+    let mut value = Some(0u32);
+    let mut is_empty = true;
+
+    for digit in digits.chars().filter(|d| *d != '_') {
+        let Some(digit) = digit.to_digit(radix) else {
+            return Err(LexerError::MalformedInteger(literal.to_string(), span));
+        };
+
+        is_empty = false;
+        value = value.and_then(|value| value.checked_mul(radix)?.checked_add(digit));
+    }
+
+    if is_empty {
+        return Err(LexerError::MalformedInteger(literal.to_string(), span));
+    }
+
+    match (value, radix) {
+        (Some(value), 10) if value <= i32::MIN.unsigned_abs() => Ok(value as i64),
+        (Some(value), 16 | 2 | 8) => Ok(value as i32 as i64),
+        _ => Err(LexerError::IntegerTooLarge(literal.to_string(), span)),
+    }
+}
+
+pub struct Lexer<'src> {
+    source: &'src str,
+    reader: Reader<'src>,
+    errors: Vec<LexerError>,
+}
+
+impl<'src> Lexer<'src> {
+    pub fn new(source: &'src str) -> Self {
+        Lexer { source, reader: Reader::new(source), errors: vec![] }
+    }
+
+    pub fn into_errors(mut self) -> Vec<LexerError> {
+        self.by_ref().for_each(drop);
+
+        self.errors
+    }
+}
+
+impl<'src> Iterator for Lexer<'src> {
+    type Item = Spanned<Token<'src>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Lexer { source, reader, errors } = self;
+
+        loop {
+            let start = reader.position;
+
+            let c = reader.next()?;
+
+            let token = match c {
+                c if c.is_whitespace() => continue,
+                // Block comments, which may span lines and nest
+                '#' if reader.next_if(|n| *n == '[').is_some() => {
+                    let opening = reader.span_from(start);
+                    let mut depth = 1;
+
+                    while depth > 0 {
+                        match reader.next() {
+                            Some('#') if reader.next_if(|n| *n == '[').is_some() => depth += 1,
+                            Some(']') if reader.next_if(|n| *n == '#').is_some() => depth -= 1,
+                            Some(_) => (),
+                            None => {
+                                errors.push(LexerError::UnterminatedComment(opening));
+                                break
+                            },
+                        }
                     }
+
+                    continue
                 }
-                
-                continue
-            }
-            // Arithmetic
-            '+' => Token::Add,
-            '-' => Token::Sub,
-            '*' => Token::Mul,
-            '/' => Token::Div,
-            // Logic
-            '?' => Token::Is,
-            '!' => Token::Not,
-            '&' => Token::And,
-            '|' => Token::Or,
-            // Brackets & Semicolon
-            '(' => Token::LParen,
-            ')' => Token::RParen,
-            '{' => Token::LBrace,
-            '}' => Token::RBrace,
-            ';' => Token::Semicolon,
-            // Comparison
-            '=' => Token::Equals,
-            '>' => Token::Greater,
-            '<' => Token::Less,
-            // Literals
-            '0'..='9' => {
-                let sub_reader = std::iter::once(c);
-
-                let digits: String = sub_reader.chain(from_fn(|| reader.next_if(|n| n.is_ascii_digit()))).collect();
-
-                match digits.parse::<i32>() { 
-                    Ok(i) => Token::Constant(i),
-                    Err(_) => return Err(LexerError::InvalidInteger),
+                // Line comments
+                '#' => {
+                    while reader.next_if(|n| *n != '\n').is_some() {}
+
+                    continue
                 }
-            },
-            // Keywords & Identifiers
-            'A'..='Z' | 'a'..='z' | '_' => {
-                let sub_reader = std::iter::once(c);
-
-                let string: String = sub_reader.chain(from_fn(|| reader.next_if(|l| l.is_ascii_alphabetic() || *l == '_'))).collect();
-
-                match string.as_str() {
-                    "let"   => Token::Let,
-                    "be"    => Token::Be,
-                    "set"   => Token::Set,
-                    "to"    => Token::To,
-                    "rep"   => Token::Rep,
-                    "print" => Token::Print,
-                    _ => Token::Variable(string),
+                // Arithmetic
+                '+' => Token::Add,
+                '-' => Token::Sub,
+                '*' => Token::Mul,
+                '/' => Token::Div,
+                '%' => Token::Mod,
+                // Logic
+                '?' => Token::Is,
+                '!' if reader.next_if(|n| *n == '=').is_some() => Token::NotEquals,
+                '!' => Token::Not,
+                '&' => Token::And,
+                '|' => Token::Or,
+                // Bitwise
+                '.' if reader.next_if(|n| *n == '&').is_some() => Token::BitAnd,
+                '.' if reader.next_if(|n| *n == '|').is_some() => Token::BitOr,
+                '^' => Token::Xor,
+                '~' => Token::BitNot,
+                '<' if reader.next_if(|n| *n == '<').is_some() => Token::Shl,
+                '>' if reader.next_if(|n| *n == '>').is_some() => Token::Shr,
+                // Brackets & Separators
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                '{' => Token::LBrace,
+                '}' => Token::RBrace,
+                '[' => Token::LBracket,
+                ']' => Token::RBracket,
+                ';' => Token::Semicolon,
+                ',' => Token::Comma,
+                // Comparison
+                '=' => Token::Equals,
+                '>' if reader.next_if(|n| *n == '=').is_some() => Token::GreaterEquals,
+                '>' => Token::Greater,
+                '<' if reader.next_if(|n| *n == '=').is_some() => Token::LessEquals,
+                '<' => Token::Less,
+                // Literals
+                '0'..='9' => {
+                    while reader.next_if(|n| n.is_ascii_alphanumeric() || *n == '_').is_some() {}
+
+                    let literal = &source[start.offset..reader.position.offset];
+
+                    match parse_integer(literal, reader.span_from(start)) {
+                        Ok(i) => Token::Constant(i),
+                        Err(e) => {
+                            errors.push(e);
+                            Token::Error
+                        },
+                    }
+                },
+                // Strings, which end at the closing quote and may not span lines
+                '"' => {
+                    let content_start = reader.position.offset;
+                    let mut content_end = content_start;
+                    // Only allocated once an escape sequence makes the content differ from the source.
+                    let mut unescaped: Option<String> = None;
+                    let mut is_valid = true;
+
+                    loop {
+                        let escape_start = reader.position;
+
+                        match reader.next_if(|n| *n != '\n') {
+                            Some('"') => break,
+                            Some('\\') => {
+                                let string = unescaped.get_or_insert_with(|| source[content_start..content_end].to_string());
+
+                                match reader.next_if(|n| *n != '\n') {
+                                    Some('n') => string.push('\n'),
+                                    Some('t') => string.push('\t'),
+                                    Some('"') => string.push('"'),
+                                    Some('\\') => string.push('\\'),
+                                    escape => {
+                                        let escape = format!("\\{}", escape.map(String::from).unwrap_or_default());
+                                        errors.push(LexerError::InvalidEscape(escape, reader.span_from(escape_start)));
+                                        is_valid = false;
+                                    },
+                                }
+                            },
+                            Some(c) => match &mut unescaped {
+                                Some(string) => string.push(c),
+                                None => content_end = reader.position.offset,
+                            },
+                            None => {
+                                errors.push(LexerError::UnterminatedString(reader.span_from(start)));
+                                is_valid = false;
+                                break
+                            },
+                        }
+                    }
+
+                    match (is_valid, unescaped) {
+                        (false, _) => Token::Error,
+                        (true, Some(string)) => Token::Str(Cow::Owned(string)),
+                        (true, None) => Token::Str(Cow::Borrowed(&source[content_start..content_end])),
+                    }
+                },
+                // Keywords & Identifiers
+                c if is_identifier_start(c) => {
+                    while reader.next_if(|l| is_identifier_continue(*l)).is_some() {}
+
+                    let string = &source[start.offset..reader.position.offset];
+
+                    match string {
+                        "let"   => Token::Let,
+                        "be"    => Token::Be,
+                        "set"   => Token::Set,
+                        "to"    => Token::To,
+                        "rep"   => Token::Rep,
+                        "as"    => Token::As,
+                        "else"  => Token::Else,
+                        "while" => Token::While,
+                        "break" => Token::Break,
+                        "continue" => Token::Continue,
+                        "fn"    => Token::Fn,
+                        "return" => Token::Return,
+                        "print" => Token::Print,
+                        "read"  => Token::Read,
+                        "eat"   => Token::Eat,
+                        _ => Token::Variable(string),
+                    }
                 }
-            }
-            x => return Err(LexerError::InvalidCharacter(x.to_string())),
-        };
+                x => {
+                    errors.push(LexerError::InvalidCharacter(x.to_string(), reader.span_from(start)));
+                    Token::Error
+                },
+            };
+
+            return Some(Spanned::new(token, reader.span_from(start)));
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lex(source: &str) -> (Vec<Token<'_>>, Vec<String>) {
+        let mut lexer = Lexer::new(source);
+        let tokens = lexer.by_ref().map(|token| token.value).collect();
+        let errors = lexer.into_errors().iter().map(|error| error.to_string()).collect();
+
+        (tokens, errors)
+    }
+
+    #[test]
+    fn reports_every_error_in_one_pass() {
+        let (tokens, errors) = lex("let x be 12ab $ 5;\nprint @ 1;\nlet y be 3;");
+
+        assert_eq!(errors, [
+            "1:10: malformed integer: 12ab",
+            "1:15: invalid character: $",
+            "2:7: invalid character: @",
+        ]);
+
+        assert_eq!(tokens, [
+            Token::Let, Token::Variable("x"), Token::Be, Token::Error, Token::Error, Token::Constant(5), Token::Semicolon,
+            Token::Print, Token::Error, Token::Constant(1), Token::Semicolon,
+            Token::Let, Token::Variable("y"), Token::Be, Token::Constant(3), Token::Semicolon,
+        ]);
+    }
+
+    #[test]
+    fn reads_radix_prefixes_and_separators() {
+        let (tokens, errors) = lex("0x7fff_ffff 0xffff_ffff 0b1_0 0o17 1_000");
+
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(tokens, [Token::Constant(2147483647), Token::Constant(-1), Token::Constant(2), Token::Constant(15), Token::Constant(1000)]);
+    }
 
-        tokens.push(token);
+    #[test]
+    fn integer_errors_cover_the_literal() {
+        let mut lexer = Lexer::new("print 0xffff_ffff, 0x1_0000_0000, 2147483648, 0o9, 0x_;");
+        lexer.by_ref().for_each(drop);
+
+        assert_eq!(lexer.into_errors(), [
+            LexerError::IntegerTooLarge("0x1_0000_0000".to_string(), Span { start: 19, end: 32, line: 1, column: 20 }),
+            LexerError::MalformedInteger("0o9".to_string(), Span { start: 46, end: 49, line: 1, column: 47 }),
+            LexerError::MalformedInteger("0x_".to_string(), Span { start: 51, end: 54, line: 1, column: 52 }),
+        ]);
+    }
+
+    #[test]
+    fn skips_nested_block_comments() {
+        let (tokens, errors) = lex("print #[ 1 #[ 2 ]# 3 ]# 4;");
+
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(tokens, [Token::Print, Token::Constant(4), Token::Semicolon]);
+    }
+
+    #[test]
+    fn unterminated_comment_ends_the_input() {
+        let (tokens, errors) = lex("print 1;\n#[ print 2; #[ nested ]#");
+
+        assert_eq!(tokens, [Token::Print, Token::Constant(1), Token::Semicolon]);
+        assert_eq!(errors, ["2:1: unterminated block comment"]);
     }
 
-    Ok(tokens)
-}
\ No newline at end of file
+    #[test]
+    fn resolves_escapes_in_strings() {
+        let (tokens, errors) = lex(r#"print "n = ", "a\tb\n\"\\";"#);
+
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(tokens, [Token::Print, Token::Str("n = ".into()), Token::Comma, Token::Str("a\tb\n\"\\".into()), Token::Semicolon]);
+    }
+
+    #[test]
+    fn string_errors_end_at_the_line() {
+        let (tokens, errors) = lex("print \"a\\q\", 1;\nprint \"open\nprint 2;");
+
+        assert_eq!(errors, ["1:9: invalid escape sequence: \\q", "2:7: unterminated string"]);
+        assert_eq!(tokens, [
+            Token::Print, Token::Error, Token::Comma, Token::Constant(1), Token::Semicolon,
+            Token::Print, Token::Error,
+            Token::Print, Token::Constant(2), Token::Semicolon,
+        ]);
+    }
+}
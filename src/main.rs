@@ -1,3 +1,4 @@
+mod span;
 mod token;
 mod lexer;
 mod ast;
@@ -7,72 +8,168 @@ mod ssa;
 mod generator;
 mod rust_transpiler;
 mod c_transpiler;
+mod mangle;
 
-use lexer::tokenize_program;
+use lexer::Lexer;
 use parser::parse_program;
-use semantic_analyzer::validate_program;
+use semantic_analyzer::{validate_program, Typing};
 use rust_transpiler::transpile_program_to_rust;
 use c_transpiler::transpile_program_to_c;
 
-use std::{env, fs, process, error};
+use std::{env, fs, io, process, error};
 
 // SSA (Single Static Assignment), hjälper optimering om varje variabel endast tilldelas en gång.
 
 // TODO: finish up the transpiler.
-// TODO: fix the issue with left recursion not allowing statements such as "let x be 1 + 2 + 5;".
-//       In the parser, functions assume that the left hand side is of a particular variant, 
-//       meaning Token::And always results in Expr::And{ Expr::Sent, Sent },
-//       instead of Expr::And{ Expr, Sent }. 
-//       This results in operators of the same order being unable to be chained together.
-//       1 * 1 * 1 and 1 + 1 + 1 does not work, but 1 * 1 + 1 * 1 does.
-//       One weird one that does not work is 1 + 1 * 1 + 1.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Stage {
+    Tokens,
+    Ast,
+    C,
+    Rust,
+}
+
+impl Stage {
+    fn from_name(name: &str) -> Option<Stage> {
+        match name {
+            "tokens" => Some(Stage::Tokens),
+            "ast" => Some(Stage::Ast),
+            "c" => Some(Stage::C),
+            "rs" => Some(Stage::Rust),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            Stage::Tokens => "tokens",
+            Stage::Ast => "ast",
+            Stage::C => "c",
+            Stage::Rust => "rs",
+        }
+    }
+}
+
+// Without a path of its own, a stage goes next to the output, or to stdout if there is none.
+struct Emit<'a> {
+    pub stage: Stage,
+    pub path: Option<&'a str>,
+}
+
+impl Emit<'_> {
+    fn destination(&self, output_path: Option<&str>) -> Option<String> {
+        match (self.path, output_path) {
+            (Some("-"), _) | (None, None) => None,
+            (Some(path), _) => Some(path.to_string()),
+            (None, Some(output_path)) => Some(format!("{}.{}", output_path, self.stage.extension())),
+        }
+    }
+}
 
 struct Config<'a> {
     pub input_path: &'a str,
-    pub output_path: &'a str,
-    pub transpiler: Option<&'a str>,
+    pub output_path: Option<&'a str>,
+    pub emits: Vec<Emit<'a>>,
+    pub typing: Typing,
 }
 
-fn parse_config(args: &[String]) -> Result<Config, &'static str> {
-    if args.len() < 3 {
-        return Err("not enough arguments");
+// Usage: `compiler input_path [output_path [c|rs]] [--strict] [--emit=tokens|ast|c|rs[=path]]...`
+fn parse_config(args: &[String]) -> Result<Config<'_>, &'static str> {
+    let mut positional = vec![];
+    let mut emits = vec![];
+    let mut typing = Typing::Lax;
+
+    for arg in args.iter().skip(1) {
+        match arg.strip_prefix("--emit=") {
+            Some(emit) => {
+                let (stage, path) = match emit.split_once('=') {
+                    Some((stage, path)) => (stage, Some(path)),
+                    None => (emit, None),
+                };
+
+                let stage = Stage::from_name(stage).ok_or("unknown stage to emit, expected one of: tokens, ast, c, rs")?;
+
+                emits.push(Emit { stage, path });
+            },
+            None if arg == "--strict" => typing = Typing::Strict,
+            None if arg.starts_with("--") => return Err("unknown option"),
+            None => positional.push(arg.as_str()),
+        }
     }
 
-    let input_path = &args[1];
-    let output_path = &args[2];
+    let (input_path, output_path) = match positional[..] {
+        [] => return Err("not enough arguments"),
+        [input_path] => (input_path, None),
+        [input_path, output_path] => (input_path, Some(output_path)),
+        [input_path, output_path, transpiler] => {
+            match Stage::from_name(transpiler) {
+                Some(stage @ (Stage::C | Stage::Rust)) => emits.push(Emit { stage, path: None }),
+                _ => return Err("unknown transpiler, expected one of: c, rs"),
+            }
+
+            (input_path, Some(output_path))
+        },
+        _ => return Err("too many arguments"),
+    };
 
-    if args.len() < 4 {
-        return Ok(Config { input_path, output_path, transpiler: None })
+    if emits.iter().filter(|emit| emit.destination(output_path).is_none()).count() > 1 {
+        return Err("only one stage can be emitted to stdout, give an output path or use --emit=<stage>=<path>");
     }
 
-    let transpiler = Some(args[3].as_ref());
+    Ok(Config { input_path, output_path, emits, typing })
+}
+
+fn emit(config: &Config, stage: Stage, output: impl FnOnce() -> String) -> io::Result<()> {
+    let emits: Vec<&Emit> = config.emits.iter().filter(|emit| emit.stage == stage).collect();
 
-    Ok(Config { input_path, output_path, transpiler })
+    if emits.is_empty() {
+        return Ok(());
+    }
+
+    let output = output();
+
+    for emit in emits {
+        match emit.destination(config.output_path) {
+            Some(path) => fs::write(path, &output)?,
+            None => print!("{output}"),
+        }
+    }
+
+    Ok(())
 }
 
 fn run(config: Config) -> Result<(), Box<dyn error::Error>> {
     let contents = fs::read_to_string(config.input_path)?;
 
-    let tokens = tokenize_program(&contents).expect("tokenization failed");
+    emit(&config, Stage::Tokens, || Lexer::new(&contents).map(|token| format!("{token}\n")).collect())?;
 
-    let program = parse_program(tokens).expect("parsing failed");
+    let mut lexer = Lexer::new(&contents);
 
-    validate_program(&program).expect("semantic analysis failed");
+    let (program, parser_errors) = parse_program(&mut lexer);
 
-    match config.transpiler {
-        Some(t) => {
-            if t == "c" {
-                std::fs::write(format!("{}.c", config.output_path), 
-                    transpile_program_to_c(&program)).expect("write to c failed");
-            }
-            if t == "rs" {
-                std::fs::write(format!("{}.rs", config.output_path), 
-                    transpile_program_to_rust(&program)).expect("write to rust failed");
-            }
-        }
-        None => (),
+    let lexer_errors = lexer.into_errors();
+
+    for e in &lexer_errors {
+        eprintln!("{e}");
+    }
+
+    for e in &parser_errors {
+        eprintln!("{e}");
+    }
+
+    if !lexer_errors.is_empty() || !parser_errors.is_empty() {
+        return Err(format!("{} lexer error(s), {} parser error(s)", lexer_errors.len(), parser_errors.len()).into())
     }
 
+    emit(&config, Stage::Ast, || format!("{program}\n"))?;
+
+    validate_program(&program, config.typing)?;
+
+    emit(&config, Stage::C, || transpile_program_to_c(&program))?;
+
+    emit(&config, Stage::Rust, || transpile_program_to_rust(&program))?;
+
     Ok(())
 }
 
@@ -81,13 +178,13 @@ fn main() {
 
     let config = parse_config(&args).unwrap_or_else(
         |e| {
-            println!("{e}");
+            eprintln!("{e}");
             process::exit(1);
         }
     );
 
     if let Err(e) = run(config) {
-        println!("{e}");
+        eprintln!("{e}");
         process::exit(1);
     }
 }
@@ -0,0 +1,36 @@
+use std::borrow::Cow;
+
+// Reserved and non-ASCII names, and names starting with `m_`, `__` or `_` and an uppercase letter, are escaped behind `m_`.
+// An escaped `_` is always followed by `_` or `u`, so made-up names like `m__n` and `m_{escaped}_f` never collide.
+pub fn mangle<'a>(name: &'a str, reserved: &[&str]) -> Cow<'a, str> {
+    let is_implementation_name = match name.as_bytes() {
+        [b'_', b'_', ..] | [b'm', b'_', ..] => true,
+        [b'_', c, ..] => c.is_ascii_uppercase(),
+        _ => false,
+    };
+
+    if name.is_ascii() && !is_implementation_name && !reserved.contains(&name) {
+        return Cow::Borrowed(name);
+    }
+
+    Cow::Owned(format!("m_{}", escape(name)))
+}
+
+// Function names are always escaped, to stay clear of everything the C headers declare.
+pub fn mangle_function(name: &str) -> String {
+    format!("m_{}_f", escape(name))
+}
+
+fn escape(name: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in name.chars() {
+        match c {
+            '_' => escaped.push_str("__"),
+            c if c.is_ascii_alphanumeric() => escaped.push(c),
+            c => escaped.push_str(&format!("_u{:06x}", c as u32)),
+        }
+    }
+
+    escaped
+}
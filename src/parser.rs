@@ -1,337 +1,1019 @@
-use std::{iter::Peekable, borrow::Cow, fmt::Display};
+use std::{iter::Peekable, borrow::Cow, fmt::Display, cell::Cell};
+
+use crate::{token::{Token, TokenKind}, span::{Span, Spanned}, ast::*};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Construct {
+    Statement,
+    LetBe,
+    SetTo,
+    Rep,
+    While,
+    Break,
+    Continue,
+    Function,
+    Return,
+    Print,
+    Read,
+    Eat,
+}
 
-use crate::{token::Token, ast::*};
+impl Display for Construct {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            Construct::Statement => "statement",
+            Construct::LetBe => "`let` statement",
+            Construct::SetTo => "`set` statement",
+            Construct::Rep => "`rep` statement",
+            Construct::While => "`while` statement",
+            Construct::Break => "`break` statement",
+            Construct::Continue => "`continue` statement",
+            Construct::Function => "`fn` declaration",
+            Construct::Return => "`return` statement",
+            Construct::Print => "`print` statement",
+            Construct::Read => "`read` statement",
+            Construct::Eat => "`eat` statement",
+        };
+
+        write!(f, "{output}")
+    }
+}
 
 #[derive(Debug, Clone, PartialEq)]
-pub enum ParserError {
+pub enum ParserError<'src> {
     UnexpectedToken {
-        expected: Token,
-        received: Token, 
+        expected: Vec<TokenKind>,
+        received: Token<'src>,
+        context: Construct,
+        span: Span,
+    },
+    // The span is that of the last token.
+    UnexpectedEnd {
+        expected: Vec<TokenKind>,
+        context: Construct,
+        span: Span,
     },
-    UnexpectedEnd,
-    InvalidToken(Token),
+    IntegerTooLarge(i64, Span),
 }
 
-impl Display for ParserError {
+impl Display for ParserError<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let output: Cow<str> = match self {
-            ParserError::UnexpectedToken { expected, received } => 
-                Cow::Owned(format!("unexpected token: {received} expected: {expected}")),
-            ParserError::UnexpectedEnd => "unexpected end".into(),
-            ParserError::InvalidToken(token) => 
-                Cow::Owned(format!("invalid token: {token}")),
+            ParserError::UnexpectedToken { expected, received, context, span } =>
+                Cow::Owned(format!("{span}: unexpected token: {} in {context}, {}", describe_received(received), describe_expected(expected))),
+            ParserError::UnexpectedEnd { expected, context, span } =>
+                Cow::Owned(format!("{span}: unexpected end in {context}, {}", describe_expected(expected))),
+            ParserError::IntegerTooLarge(constant, span) =>
+                Cow::Owned(format!("{span}: integer too large: {constant}, allowed range is -2_147_483_648 to 2_147_483_647")),
         };
 
         write!(f, "{output}")
     }
 }
 
-pub fn parse_program(tokens: Vec<Token>) -> Result<Program, ParserError> {
-    let mut iter = tokens.into_iter().peekable();
+impl std::error::Error for ParserError<'_> {}
 
-    let program = parse_block(&mut iter, false)?;
+fn describe_received(received: &Token) -> String {
+    match received {
+        Token::Constant(constant) => format!("constant {constant}"),
+        Token::Variable(variable) => format!("identifier {variable}"),
+        _ => received.kind().to_string(),
+    }
+}
 
-    Ok(Program{ block: program })
+fn describe_expected(expected: &[TokenKind]) -> String {
+    let any_operator = BINARY_OPERATORS.iter().all(|kind| expected.contains(kind));
+
+    let mut names: Vec<String> = expected.iter()
+        .filter(|kind| !(any_operator && BINARY_OPERATORS.contains(kind)))
+        .map(|kind| kind.to_string())
+        .collect();
+
+    if any_operator {
+        names.push("operator".to_string());
+    }
+
+    match names.as_slice() {
+        [name] => format!("expected: {name}"),
+        _ => format!("expected one of: {}", names.join(", ")),
+    }
 }
 
-fn parse_block<T>(tokens: &mut Peekable<T>, is_enclosed: bool) -> Result<Block, ParserError>  where T: Iterator<Item = Token> {
-    let mut statements = vec![];
+const STATEMENT_START: [TokenKind; 11] = [
+    TokenKind::Let, TokenKind::Set, TokenKind::Rep, TokenKind::While,
+    TokenKind::Break, TokenKind::Continue, TokenKind::Fn, TokenKind::Return, TokenKind::Print, TokenKind::Read, TokenKind::Eat,
+];
+
+const FACT_START: [TokenKind; 8] = [
+    TokenKind::Variable, TokenKind::Constant, TokenKind::LParen,
+    TokenKind::Add, TokenKind::Sub, TokenKind::Not, TokenKind::Is, TokenKind::BitNot,
+];
+
+const BINARY_OPERATORS: [TokenKind; 18] = [
+    TokenKind::And, TokenKind::Or,
+    TokenKind::Equals, TokenKind::Greater, TokenKind::Less,
+    TokenKind::NotEquals, TokenKind::GreaterEquals, TokenKind::LessEquals,
+    TokenKind::Add, TokenKind::Sub, TokenKind::BitOr, TokenKind::Xor,
+    TokenKind::Mul, TokenKind::Div, TokenKind::Mod, TokenKind::BitAnd, TokenKind::Shl, TokenKind::Shr,
+];
+
+pub fn parse_program<'src>(tokens: impl IntoIterator<Item = Spanned<Token<'src>>>) -> (Program, Vec<ParserError<'src>>) {
+    // Remembers the last token read, which is where the input ends if it ends early.
+    let last = Cell::new(Span::default());
+    let mut iter = tokens.into_iter().inspect(|token| last.set(token.span)).peekable();
+    let mut errors = vec![];
+
+    // A top level block always parses, failed statements are recovered from within it.
+    let block = parse_block(&mut iter, None, &mut errors).unwrap_or_default();
+
+    for error in &mut errors {
+        if let ParserError::UnexpectedEnd { span, .. } = error {
+            *span = last.get();
+        }
+    }
 
-    if is_enclosed {
-        match tokens.next() {
-            Some(token) => match token {
-                Token::LBrace => (),
-                _ => return Err(ParserError::UnexpectedToken { expected: Token::LBrace, received: token })
-            },
-            None => return Err(ParserError::UnexpectedEnd),
+    // Invalid tokens have already been reported by the lexer.
+    errors.retain(|error| !matches!(error, ParserError::UnexpectedToken { received: Token::Error, .. }));
+
+    (Program{ block }, errors)
+}
+
+fn unexpected<'src, T>(tokens: &mut Peekable<T>, expected: Vec<TokenKind>, context: Construct) -> ParserError<'src> where T: Iterator<Item = Spanned<Token<'src>>> {
+    match tokens.peek() {
+        Some(token) => ParserError::UnexpectedToken { expected, received: token.value.clone(), context, span: token.span },
+        None => ParserError::UnexpectedEnd { expected, context, span: Span::default() },
+    }
+}
+
+// Any other token is left in place, so recovery can resume from it.
+fn expect<'src, T>(tokens: &mut Peekable<T>, expected: TokenKind, context: Construct) -> Result<Span, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    match tokens.next_if(|token| token.value.kind() == expected) {
+        Some(token) => Ok(token.span),
+        None => Err(unexpected(tokens, vec![expected], context)),
+    }
+}
+
+fn expect_after_expr<'src, T>(tokens: &mut Peekable<T>, expected: TokenKind, context: Construct) -> Result<Span, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    match tokens.next_if(|token| token.value.kind() == expected) {
+        Some(token) => Ok(token.span),
+        None => Err(unexpected(tokens, [&[expected], &BINARY_OPERATORS[..]].concat(), context)),
+    }
+}
+
+fn expect_variable<'src, T>(tokens: &mut Peekable<T>, context: Construct) -> Result<String, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    match tokens.next_if(|token| matches!(token.value, Token::Variable(_))) {
+        Some(Spanned { value: Token::Variable(variable), .. }) => Ok(variable.to_string()),
+        _ => Err(unexpected(tokens, vec![TokenKind::Variable], context)),
+    }
+}
+
+// Skips past a `;`, or up to a statement keyword or the `}` closing the block, skipping nested blocks whole.
+fn synchronize<'src, T>(tokens: &mut Peekable<T>, is_enclosed: bool) -> Option<Span> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let mut last = None;
+    let mut depth = 0;
+
+    while let Some(token) = tokens.peek() {
+        match token.value {
+            Token::RBrace if depth == 0 && is_enclosed => break,
+            _ if depth == 0 && STATEMENT_START.contains(&token.value.kind()) => break,
+            Token::LBrace => depth += 1,
+            Token::RBrace if depth > 0 => depth -= 1,
+            _ => (),
+        }
+
+        let is_end = token.value == Token::Semicolon && depth == 0;
+
+        last = tokens.next().map(|token| token.span);
+
+        if is_end {
+            break
         }
     }
 
+    last
+}
+
+fn parse_block<'src, T>(tokens: &mut Peekable<T>, enclosing: Option<Construct>, errors: &mut Vec<ParserError<'src>>) -> Result<Block, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let mut statements = vec![];
+
+    let mut span = match tokens.peek() {
+        Some(token) => token.span,
+        None => Span::default(),
+    };
+
     'statements: loop {
-        match tokens.peek() {
-            Some(token) if *token == Token::RBrace && is_enclosed => {
+        let start = match (tokens.peek(), enclosing) {
+            (Some(token), Some(_)) if token.value == Token::RBrace => {
+                span = span.to(token.span);
                 tokens.next();
                 break 'statements
             },
-            None => if is_enclosed {
-                return Err(ParserError::UnexpectedEnd)
-            } else {
-                break 'statements
+            (Some(token), _) => token.span,
+            (None, Some(context)) => return Err(unexpected(tokens, [&STATEMENT_START[..], &[TokenKind::RBrace]].concat(), context)),
+            (None, None) => break 'statements,
+        };
+
+        let statement = match parse_statement(tokens, enclosing.is_some(), errors) {
+            Ok(statement) => statement,
+            Err(error) => {
+                errors.push(error);
+                let end = synchronize(tokens, enclosing.is_some()).unwrap_or(start);
+                Statement::Error(start.to(end))
             },
-            _ => (),
-        }
+        };
 
-        match parse_statement(tokens) {
-            Ok(statement) => {
-                statements.push(statement);
-            }
-            Err(e) => return Err(e),
-        }
+        span = span.to(statement.span());
+        statements.push(statement);
     }
 
-    Ok(Block{ statements })
+    Ok(Block{ statements, span })
 }
 
-fn parse_statement<T>(tokens: &mut Peekable<T>) -> Result<Statement, ParserError> where T: Iterator<Item = Token> {
+fn parse_statement<'src, T>(tokens: &mut Peekable<T>, is_enclosed: bool, errors: &mut Vec<ParserError<'src>>) -> Result<Statement, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let mut expected = STATEMENT_START.to_vec();
+
+    if is_enclosed {
+        expected.push(TokenKind::RBrace);
+    }
+
     let statement = match tokens.next() {
-        Some(token) => match token {
-            Token::Let      => parse_let_be(tokens)?,
-            Token::Set      => parse_set_to(tokens)?,
-            Token::Rep      => parse_rep(tokens)?,
-            Token::Print    => parse_print(tokens)?,
-            t => return Err(ParserError::InvalidToken(t)),
+        Some(token) => match token.value {
+            Token::Let      => parse_let_be(tokens, token.span)?,
+            Token::Set      => parse_set_to(tokens, token.span)?,
+            Token::Rep      => parse_rep(tokens, token.span, errors)?,
+            Token::While    => parse_while(tokens, token.span, errors)?,
+            Token::Break    => Statement::Break(token.span.to(expect(tokens, TokenKind::Semicolon, Construct::Break)?)),
+            Token::Continue => Statement::Continue(token.span.to(expect(tokens, TokenKind::Semicolon, Construct::Continue)?)),
+            Token::Fn       => parse_function(tokens, token.span, errors)?,
+            Token::Return   => parse_return(tokens, token.span)?,
+            Token::Print    => parse_print(tokens, token.span)?,
+            Token::Read     => parse_read(tokens, token.span)?,
+            Token::Eat      => parse_eat(tokens, token.span)?,
+            t => return Err(ParserError::UnexpectedToken { expected, received: t, context: Construct::Statement, span: token.span }),
         },
-        None => return Err(ParserError::UnexpectedEnd),
+        None => return Err(unexpected(tokens, expected, Construct::Statement)),
     };
 
     Ok(statement)
 }
 
-fn parse_let_be<T>(tokens: &mut Peekable<T>) -> Result<Statement, ParserError> where T: Iterator<Item = Token> {
-    let variable = match tokens.next() {
-        Some(token) => match token {
-            Token::Variable(variable) => variable,
-            _ => return Err(ParserError::UnexpectedToken { expected: Token::Variable("".to_string()), received: token })
-        },
-        None => return Err(ParserError::UnexpectedEnd),
-    };
+fn parse_let_be<'src, T>(tokens: &mut Peekable<T>, start: Span) -> Result<Statement, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let variable = expect_variable(tokens, Construct::LetBe)?;
 
-    match tokens.next() {
-        Some(token) => match token {
-            Token::Be => (),
-            _ => return Err(ParserError::UnexpectedToken { expected: Token::Be, received: token })
-        },
-        None => return Err(ParserError::UnexpectedEnd),
+    expect(tokens, TokenKind::Be, Construct::LetBe)?;
+
+    if tokens.next_if(|token| token.value == Token::LBracket).is_some() {
+        let size = match tokens.next_if(|token| matches!(token.value, Token::Constant(_))) {
+            Some(Spanned { value: Token::Constant(size), span }) =>
+                i32::try_from(size).map_err(|_| ParserError::IntegerTooLarge(size, span))?,
+            _ => return Err(unexpected(tokens, vec![TokenKind::Constant], Construct::LetBe)),
+        };
+
+        expect(tokens, TokenKind::RBracket, Construct::LetBe)?;
+
+        let end = expect(tokens, TokenKind::Semicolon, Construct::LetBe)?;
+
+        return Ok(Statement::LetBeArray(LetBeArray { variable, size, span: start.to(end) }));
     }
 
-    let expr = match tokens.peek() {
-        Some(_) => parse_expr(tokens)?,
-        None => return Err(ParserError::UnexpectedEnd),
-    };
+    if !tokens.peek().is_some_and(|token| FACT_START.contains(&token.value.kind()) || token.value == Token::Error) {
+        return Err(unexpected(tokens, [&FACT_START[..], &[TokenKind::LBracket]].concat(), Construct::LetBe));
+    }
 
-    match tokens.next() {
-        Some(token) => match token {
-            Token::Semicolon => (),
-            _ => return Err(ParserError::UnexpectedToken { expected: Token::Semicolon, received: token })
-        },
-        None => return Err(ParserError::UnexpectedEnd),
-    };
+    let expr = parse_expr(tokens, Construct::LetBe)?;
+
+    let end = expect_after_expr(tokens, TokenKind::Semicolon, Construct::LetBe)?;
 
-    Ok(Statement::LetBe(LetBe { variable, expr }))
+    Ok(Statement::LetBe(LetBe { variable, expr, span: start.to(end) }))
 }
 
-fn parse_set_to<T>(tokens: &mut Peekable<T>) -> Result<Statement, ParserError> where T: Iterator<Item = Token> {
-    let variable = match tokens.next() {
-        Some(token) => match token {
-            Token::Variable(variable) => variable,
-            _ => return Err(ParserError::UnexpectedToken { expected: Token::Variable("".to_string()), received: token })
-        },
-        None => return Err(ParserError::UnexpectedEnd),
-    };
+fn parse_set_to<'src, T>(tokens: &mut Peekable<T>, start: Span) -> Result<Statement, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let variable = expect_variable(tokens, Construct::SetTo)?;
 
-    match tokens.next() {
-        Some(token) => match token {
-            Token::To => (),
-            _ => return Err(ParserError::UnexpectedToken { expected: Token::Be, received: token })
-        },
-        None => return Err(ParserError::UnexpectedEnd),
-    }
+    let index = match tokens.next_if(|token| matches!(token.value, Token::LBracket | Token::To)) {
+        Some(Spanned { value: Token::LBracket, .. }) => {
+            let index = parse_expr(tokens, Construct::SetTo)?;
 
-    let expr = match tokens.peek() {
-        Some(_) => parse_expr(tokens)?,
-        None => return Err(ParserError::UnexpectedEnd),
-    };
+            expect_after_expr(tokens, TokenKind::RBracket, Construct::SetTo)?;
+            expect(tokens, TokenKind::To, Construct::SetTo)?;
 
-    match tokens.next() {
-        Some(token) => match token {
-            Token::Semicolon => (),
-            _ => return Err(ParserError::UnexpectedToken { expected: Token::Semicolon, received: token })
+            Some(index)
         },
-        None => return Err(ParserError::UnexpectedEnd),
+        Some(_) => None,
+        None => return Err(unexpected(tokens, vec![TokenKind::LBracket, TokenKind::To], Construct::SetTo)),
     };
 
-    Ok(Statement::SetTo(SetTo { variable, expr }))
+    let expr = parse_expr(tokens, Construct::SetTo)?;
+
+    let end = expect_after_expr(tokens, TokenKind::Semicolon, Construct::SetTo)?;
+
+    let span = start.to(end);
+
+    match index {
+        Some(index) => Ok(Statement::SetIndexTo(SetIndexTo { variable, index, expr, span })),
+        None => Ok(Statement::SetTo(SetTo { variable, expr, span })),
+    }
 }
 
-fn parse_rep<T>(tokens: &mut Peekable<T>) -> Result<Statement, ParserError> where T: Iterator<Item = Token> {
-    let expr = match tokens.peek() {
-        Some(_) => parse_expr(tokens)?,
-        None => return Err(ParserError::UnexpectedEnd),
+fn parse_rep<'src, T>(tokens: &mut Peekable<T>, start: Span, errors: &mut Vec<ParserError<'src>>) -> Result<Statement, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let expr = parse_expr(tokens, Construct::Rep)?;
+
+    let counter = match tokens.next_if(|token| token.value == Token::As) {
+        Some(_) => Some(expect_variable(tokens, Construct::Rep)?),
+        None => None,
     };
 
-    let block = match tokens.peek() {
-        Some(_) => parse_block(tokens, true)?,
-        None => return Err(ParserError::UnexpectedEnd),
+    let open = match (&counter, tokens.next_if(|token| token.value == Token::LBrace)) {
+        (_, Some(token)) => token.span,
+        (Some(_), None) => return Err(unexpected(tokens, vec![TokenKind::LBrace], Construct::Rep)),
+        (None, None) => return Err(unexpected(tokens, [&[TokenKind::LBrace, TokenKind::As], &BINARY_OPERATORS[..]].concat(), Construct::Rep)),
     };
 
-    Ok(Statement::Rep(Rep { expr, block }))
-}
+    let mut block = parse_block(tokens, Some(Construct::Rep), errors)?;
+    block.span = open.to(block.span);
 
-fn parse_print<T>(tokens: &mut Peekable<T>) -> Result<Statement, ParserError> where T: Iterator<Item = Token> {
-    let expr = match tokens.peek() {
-        Some(_) => parse_expr(tokens)?,
-        None => return Err(ParserError::UnexpectedEnd),
-    };
+    let mut span = start.to(block.span);
+
+    let else_block = match tokens.next_if(|token| token.value == Token::Else) {
+        Some(_) => {
+            let open = expect(tokens, TokenKind::LBrace, Construct::Rep)?;
+
+            let mut else_block = parse_block(tokens, Some(Construct::Rep), errors)?;
+            else_block.span = open.to(else_block.span);
 
-    match tokens.next() {
-        Some(token) => match token {
-            Token::Semicolon => (),
-            _ => return Err(ParserError::UnexpectedToken { expected: Token::Semicolon, received: token })
+            span = span.to(else_block.span);
+            Some(else_block)
         },
-        None => return Err(ParserError::UnexpectedEnd),
+        None => None,
     };
 
-    Ok(Statement::Print(Print { expr } ))
+    Ok(Statement::Rep(Rep { expr, counter, block, else_block, span }))
 }
 
-fn parse_expr<T>(tokens: &mut Peekable<T>) -> Result<Expr, ParserError> where T: Iterator<Item = Token> {
-    let mut expr = match tokens.peek() {
-        Some(_) => Expr::Sent(parse_sent(tokens)?),
-        None => return Err(ParserError::UnexpectedEnd),
-    };
+fn parse_while<'src, T>(tokens: &mut Peekable<T>, start: Span, errors: &mut Vec<ParserError<'src>>) -> Result<Statement, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let expr = parse_expr(tokens, Construct::While)?;
 
-    while let Some(token) = tokens.peek() {
-        match token {
-            Token::And => {
-                tokens.next();
-                expr = Expr::And(Box::new(expr), parse_sent(tokens)?)
-            },
-            Token::Or => {
-                tokens.next();
-                expr = Expr::Or(Box::new(expr), parse_sent(tokens)?)
-            },
-            _ => break,
+    let open = expect_after_expr(tokens, TokenKind::LBrace, Construct::While)?;
+
+    let mut block = parse_block(tokens, Some(Construct::While), errors)?;
+    block.span = open.to(block.span);
+
+    let span = start.to(block.span);
+
+    Ok(Statement::While(While { expr, block, span }))
+}
+
+fn parse_function<'src, T>(tokens: &mut Peekable<T>, start: Span, errors: &mut Vec<ParserError<'src>>) -> Result<Statement, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let name = expect_variable(tokens, Construct::Function)?;
+
+    expect(tokens, TokenKind::LParen, Construct::Function)?;
+
+    let mut parameters = vec![];
+
+    if tokens.next_if(|token| token.value == Token::RParen).is_none() {
+        if !matches!(tokens.peek(), Some(Spanned { value: Token::Variable(_), .. })) {
+            return Err(unexpected(tokens, vec![TokenKind::Variable, TokenKind::RParen], Construct::Function));
+        }
+
+        loop {
+            parameters.push(expect_variable(tokens, Construct::Function)?);
+
+            match tokens.next_if(|token| matches!(token.value, Token::Comma | Token::RParen)) {
+                Some(Spanned { value: Token::Comma, .. }) => (),
+                Some(_) => break,
+                None => return Err(unexpected(tokens, vec![TokenKind::Comma, TokenKind::RParen], Construct::Function)),
+            }
         }
     }
 
-    Ok(expr)
+    let open = expect(tokens, TokenKind::LBrace, Construct::Function)?;
+
+    let mut block = parse_block(tokens, Some(Construct::Function), errors)?;
+    block.span = open.to(block.span);
+
+    let span = start.to(block.span);
+
+    Ok(Statement::Function(Function { name, parameters, block, span }))
 }
 
-fn parse_sent<T>(tokens: &mut Peekable<T>) -> Result<Sent, ParserError> where T: Iterator<Item = Token> {
-    let mut sent = match tokens.peek() {
-        Some(_) => Sent::Comp(parse_comp(tokens)?),
-        None => return Err(ParserError::UnexpectedEnd),
-    };
+fn parse_return<'src, T>(tokens: &mut Peekable<T>, start: Span) -> Result<Statement, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let expr = parse_expr(tokens, Construct::Return)?;
 
-    while let Some(token) = tokens.peek() {
-        match token {
-            Token::Equals => {
-                tokens.next();
-                sent = Sent::Equals(Box::new(sent), parse_comp(tokens)?)
-            },
-            Token::Greater => {
-                tokens.next();
-                sent = Sent::Greater(Box::new(sent), parse_comp(tokens)?)
+    let end = expect_after_expr(tokens, TokenKind::Semicolon, Construct::Return)?;
+
+    Ok(Statement::Return(Return { expr, span: start.to(end) }))
+}
+
+fn parse_print<'src, T>(tokens: &mut Peekable<T>, start: Span) -> Result<Statement, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let mut items = vec![];
+
+    let end = loop {
+        let item = match tokens.next_if(|token| matches!(token.value, Token::Str(_))) {
+            Some(Spanned { value: Token::Str(string), span }) => PrintItem::Str(string.into_owned(), span),
+            _ if !tokens.peek().is_some_and(|token| FACT_START.contains(&token.value.kind())) =>
+                return Err(unexpected(tokens, [&FACT_START[..], &[TokenKind::Str]].concat(), Construct::Print)),
+            _ => PrintItem::Expr(Box::new(parse_expr(tokens, Construct::Print)?)),
+        };
+
+        match tokens.next_if(|token| matches!(token.value, Token::Comma | Token::Semicolon)) {
+            Some(Spanned { value: Token::Comma, .. }) => (),
+            Some(token) => {
+                items.push(item);
+                break token.span
             },
-            Token::Less => {
-                tokens.next();
-                sent = Sent::Less(Box::new(sent), parse_comp(tokens)?)
+            None => {
+                let mut expected = vec![TokenKind::Comma, TokenKind::Semicolon];
+
+                if let PrintItem::Expr(_) = item {
+                    expected.extend(BINARY_OPERATORS);
+                }
+
+                return Err(unexpected(tokens, expected, Construct::Print))
             },
-            _ => break,
         }
-    }
 
-    Ok(sent)
+        items.push(item);
+    };
+
+    Ok(Statement::Print(Print { items, span: start.to(end) }))
 }
 
-fn parse_comp<T>(tokens: &mut Peekable<T>) -> Result<Comp, ParserError> where T: Iterator<Item = Token> {
-    let mut comp = match tokens.peek() {
-        Some(_) => Comp::Term(parse_term(tokens)?),
-        None => return Err(ParserError::UnexpectedEnd),
-    };
+fn parse_read<'src, T>(tokens: &mut Peekable<T>, start: Span) -> Result<Statement, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let variable = expect_variable(tokens, Construct::Read)?;
 
-    while let Some(token) = tokens.peek() {
-        match token {
-            Token::Add => {
-                tokens.next();
-                comp = Comp::Add(Box::new(comp), parse_term(tokens)?)
-            },
-            Token::Sub => {
-                tokens.next();
-                comp = Comp::Sub(Box::new(comp), parse_term(tokens)?)
-            },
-            _ => break,
+    let end = expect(tokens, TokenKind::Semicolon, Construct::Read)?;
+
+    Ok(Statement::Read(Read { variable, span: start.to(end) }))
+}
+
+fn parse_eat<'src, T>(tokens: &mut Peekable<T>, start: Span) -> Result<Statement, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let variable = expect_variable(tokens, Construct::Eat)?;
+
+    let end = expect(tokens, TokenKind::Semicolon, Construct::Eat)?;
+
+    Ok(Statement::Eat(Eat { variable, span: start.to(end) }))
+}
+
+enum Operand {
+    Expr(Expr),
+    Sent(Sent),
+    Comp(Comp),
+    Term(Term),
+    Fact(Fact),
+}
+
+impl Operand {
+    fn span(&self) -> Span {
+        match self {
+            Operand::Expr(expr) => expr.span(),
+            Operand::Sent(sent) => sent.span(),
+            Operand::Comp(comp) => comp.span(),
+            Operand::Term(term) => term.span(),
+            Operand::Fact(fact) => fact.span(),
+        }
+    }
+
+    // Lower levels are wrapped to fit a higher level, as in `Expr::Sent(Sent::Comp(..))`.
+    // Every operator is left associative, so an operand is never above the level it is put into.
+
+    fn into_expr(self) -> Expr {
+        match self {
+            Operand::Expr(expr) => expr,
+            operand => Expr::Sent(operand.into_sent()),
         }
     }
 
-    Ok(comp)
+    fn into_sent(self) -> Sent {
+        match self {
+            Operand::Sent(sent) => sent,
+            operand => Sent::Comp(operand.into_comp()),
+        }
+    }
+
+    fn into_comp(self) -> Comp {
+        match self {
+            Operand::Comp(comp) => comp,
+            operand => Comp::Term(operand.into_term()),
+        }
+    }
+
+    fn into_term(self) -> Term {
+        match self {
+            Operand::Term(term) => term,
+            operand => Term::Fact(operand.into_fact()),
+        }
+    }
+
+    fn into_fact(self) -> Fact {
+        match self {
+            Operand::Fact(fact) => fact,
+            _ => unreachable!("operands are never above the level they are put into"),
+        }
+    }
+}
+
+type Build = fn(Operand, Operand, Span) -> Operand;
+
+struct BinaryOperator {
+    precedence: u8,
+    build: Build,
 }
 
-fn parse_term<T>(tokens: &mut Peekable<T>) -> Result<Term, ParserError> where T: Iterator<Item = Token> {
-    let mut term = match tokens.peek() {
-        Some(_) => Term::Fact(parse_fact(tokens)?),
-        None => return Err(ParserError::UnexpectedEnd),
+// Higher precedence binds tighter, and the levels follow the README.
+fn binary_operator(token: &Token) -> Option<BinaryOperator> {
+    let (precedence, build): (u8, Build) = match token {
+        Token::And              => (1, |l, r, span| Operand::Expr(Expr::And(Box::new(l.into_expr()), r.into_sent(), span))),
+        Token::Or               => (1, |l, r, span| Operand::Expr(Expr::Or(Box::new(l.into_expr()), r.into_sent(), span))),
+        Token::Equals           => (2, |l, r, span| Operand::Sent(Sent::Equals(Box::new(l.into_sent()), r.into_comp(), span))),
+        Token::Greater          => (2, |l, r, span| Operand::Sent(Sent::Greater(Box::new(l.into_sent()), r.into_comp(), span))),
+        Token::Less             => (2, |l, r, span| Operand::Sent(Sent::Less(Box::new(l.into_sent()), r.into_comp(), span))),
+        Token::NotEquals        => (2, |l, r, span| Operand::Sent(Sent::NotEquals(Box::new(l.into_sent()), r.into_comp(), span))),
+        Token::GreaterEquals    => (2, |l, r, span| Operand::Sent(Sent::GreaterEquals(Box::new(l.into_sent()), r.into_comp(), span))),
+        Token::LessEquals       => (2, |l, r, span| Operand::Sent(Sent::LessEquals(Box::new(l.into_sent()), r.into_comp(), span))),
+        Token::Add              => (3, |l, r, span| Operand::Comp(Comp::Add(Box::new(l.into_comp()), r.into_term(), span))),
+        Token::Sub              => (3, |l, r, span| Operand::Comp(Comp::Sub(Box::new(l.into_comp()), r.into_term(), span))),
+        Token::BitOr            => (3, |l, r, span| Operand::Comp(Comp::BitOr(Box::new(l.into_comp()), r.into_term(), span))),
+        Token::Xor              => (3, |l, r, span| Operand::Comp(Comp::Xor(Box::new(l.into_comp()), r.into_term(), span))),
+        Token::Mul              => (4, |l, r, span| Operand::Term(Term::Mul(Box::new(l.into_term()), r.into_fact(), span))),
+        Token::Div              => (4, |l, r, span| Operand::Term(Term::Div(Box::new(l.into_term()), r.into_fact(), span))),
+        Token::Mod              => (4, |l, r, span| Operand::Term(Term::Mod(Box::new(l.into_term()), r.into_fact(), span))),
+        Token::BitAnd           => (4, |l, r, span| Operand::Term(Term::BitAnd(Box::new(l.into_term()), r.into_fact(), span))),
+        Token::Shl              => (4, |l, r, span| Operand::Term(Term::Shl(Box::new(l.into_term()), r.into_fact(), span))),
+        Token::Shr              => (4, |l, r, span| Operand::Term(Term::Shr(Box::new(l.into_term()), r.into_fact(), span))),
+        _ => return None,
     };
 
-    while let Some(token) = tokens.peek() {
-        match token {
-            Token::Mul => {
-                tokens.next();
-                term = Term::Mul(Box::new(term), parse_fact(tokens)?)
-            },
-            Token::Div => {
-                tokens.next();
-                term = Term::Div(Box::new(term), parse_fact(tokens)?)
-            },
-            _ => break,
+    Some(BinaryOperator { precedence, build })
+}
+
+fn parse_expr<'src, T>(tokens: &mut Peekable<T>, context: Construct) -> Result<Expr, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    Ok(parse_binary(tokens, 0, context)?.into_expr())
+}
+
+fn parse_binary<'src, T>(tokens: &mut Peekable<T>, min_precedence: u8, context: Construct) -> Result<Operand, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let mut lhs = Operand::Fact(parse_fact(tokens, context)?);
+
+    while let Some(operator) = tokens.peek().and_then(|token| binary_operator(&token.value)) {
+        if operator.precedence < min_precedence {
+            break
         }
+
+        tokens.next();
+
+        let rhs = parse_binary(tokens, operator.precedence + 1, context)?;
+
+        let span = lhs.span().to(rhs.span());
+        lhs = (operator.build)(lhs, rhs, span);
     }
 
-    Ok(term)
+    Ok(lhs)
 }
 
-fn parse_fact<T>(tokens: &mut Peekable<T>) -> Result<Fact, ParserError> where T: Iterator<Item = Token> {
+fn parse_fact<'src, T>(tokens: &mut Peekable<T>, context: Construct) -> Result<Fact, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
     let fact = match tokens.peek() {
-        Some(token) => match token {
-            Token::Is => {
-                tokens.next();
-                Fact::Is(parse_prim(tokens)?)
-            },
-            Token::Not => {
-                tokens.next();
-                Fact::Not(parse_prim(tokens)?)
-            },
-            Token::Add => {
-                tokens.next();
-                Fact::Pos(parse_prim(tokens)?)
-            },
-            Token::Sub => {
-                tokens.next();
-                Fact::Neg(parse_prim(tokens)?)
-            },
-            Token::LParen | Token::Constant(_) | Token::Variable(_) => Fact::Prim(parse_prim(tokens)?),
-            _ => return Err(ParserError::InvalidToken(token.clone())),
+        Some(token) => {
+            let start = token.span;
+
+            match token.value {
+                Token::Is => {
+                    tokens.next();
+                    let fact = parse_fact(tokens, context)?;
+                    let span = start.to(fact.span());
+                    Fact::Is(Box::new(fact), span)
+                },
+                Token::Not => {
+                    tokens.next();
+                    let fact = parse_fact(tokens, context)?;
+                    let span = start.to(fact.span());
+                    Fact::Not(Box::new(fact), span)
+                },
+                Token::Add => {
+                    tokens.next();
+                    let fact = parse_fact(tokens, context)?;
+                    let span = start.to(fact.span());
+                    Fact::Pos(Box::new(fact), span)
+                },
+                Token::BitNot => {
+                    tokens.next();
+                    let fact = parse_fact(tokens, context)?;
+                    let span = start.to(fact.span());
+                    Fact::BitNot(Box::new(fact), span)
+                },
+                Token::Sub => {
+                    tokens.next();
+
+                    // Negative literals are folded, which is the only way to write i32::MIN.
+                    if let Some(Spanned { value: Token::Constant(constant), span: end }) = tokens.peek() {
+                        if let Ok(constant) = i32::try_from(-constant) {
+                            let span = start.to(*end);
+                            tokens.next();
+                            return Ok(Fact::Prim(Prim::Constant(constant, span)));
+                        }
+                    }
+
+                    let fact = parse_fact(tokens, context)?;
+                    let span = start.to(fact.span());
+
+                    // Negating i32::MIN overflows, and Rust refuses to compile it.
+                    if is_i32_min(&fact) {
+                        return Err(ParserError::IntegerTooLarge(-i64::from(i32::MIN), span));
+                    }
+
+                    Fact::Neg(Box::new(fact), span)
+                },
+                Token::LParen | Token::Constant(_) | Token::Variable(_) | Token::Error => Fact::Prim(parse_prim(tokens, context)?),
+                _ => return Err(unexpected(tokens, FACT_START.to_vec(), context)),
+            }
         }
-        None => return Err(ParserError::UnexpectedEnd),
+        None => return Err(unexpected(tokens, FACT_START.to_vec(), context)),
     };
 
     Ok(fact)
 }
 
-fn parse_prim<T>(tokens: &mut Peekable<T>) -> Result<Prim, ParserError> where T: Iterator<Item = Token> {
+// Looks through parentheses and unary plus, which leave the value as it is.
+fn is_i32_min(fact: &Fact) -> bool {
+    match fact {
+        Fact::Prim(Prim::Constant(constant, _)) => *constant == i32::MIN,
+        Fact::Prim(Prim::Expr(expr, _)) => match &**expr {
+            Expr::Sent(Sent::Comp(Comp::Term(Term::Fact(fact)))) => is_i32_min(fact),
+            _ => false,
+        },
+        Fact::Pos(fact, _) => is_i32_min(fact),
+        _ => false,
+    }
+}
+
+fn parse_prim<'src, T>(tokens: &mut Peekable<T>, context: Construct) -> Result<Prim, ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
     let prim = match tokens.next() {
-        Some(token) => match token {
+        Some(token) => match token.value {
             Token::LParen => {
-                let expr = parse_expr(tokens)?;
-
-                match tokens.next() {
-                    Some(token) => match token {
-                        Token::RParen => (),
-                        _ => return Err(ParserError::UnexpectedToken{expected: Token::RParen, received: token}), 
-                    },
-                    None => return Err(ParserError::UnexpectedEnd),
-                }
+                let expr = parse_expr(tokens, context)?;
+
+                let end = expect_after_expr(tokens, TokenKind::RParen, context)?;
+
+                Prim::Expr(Box::new(expr), token.span.to(end))
+            },
+            Token::Constant(constant) => match i32::try_from(constant) {
+                Ok(constant) => Prim::Constant(constant, token.span),
+                Err(_) => return Err(ParserError::IntegerTooLarge(constant, token.span)),
+            },
+            Token::Variable(name) if tokens.next_if(|token| token.value == Token::LParen).is_some() => {
+                let (arguments, end) = parse_arguments(tokens, context)?;
 
-                Prim::Expr(Box::new(expr))
+                Prim::Call(name.to_string(), arguments, token.span.to(end))
             },
-            Token::Constant(constant) => {
-                Prim::Constant(constant)
+            Token::Variable(name) if tokens.next_if(|token| token.value == Token::LBracket).is_some() => {
+                let index = parse_expr(tokens, context)?;
+
+                let end = expect_after_expr(tokens, TokenKind::RBracket, context)?;
+
+                Prim::Index(name.to_string(), Box::new(index), token.span.to(end))
             },
-            Token::Variable(variable) => Prim::Variable(variable.to_string()),
-            _ => return Err(ParserError::InvalidToken(token.clone())),
+            Token::Variable(variable) => Prim::Variable(variable.to_string(), token.span),
+            // The lexer has already reported this token, stand in with a constant to keep parsing.
+            Token::Error => Prim::Constant(0, token.span),
+            t => return Err(ParserError::UnexpectedToken { expected: FACT_START.to_vec(), received: t, context, span: token.span }),
         }
-        None => return Err(ParserError::UnexpectedEnd),
+        None => return Err(unexpected(tokens, FACT_START.to_vec(), context)),
     };
 
     Ok(prim)
 }
+
+fn parse_arguments<'src, T>(tokens: &mut Peekable<T>, context: Construct) -> Result<(Vec<Expr>, Span), ParserError<'src>> where T: Iterator<Item = Spanned<Token<'src>>> {
+    let mut arguments = vec![];
+
+    if let Some(token) = tokens.next_if(|token| token.value == Token::RParen) {
+        return Ok((arguments, token.span));
+    }
+
+    if !tokens.peek().is_some_and(|token| FACT_START.contains(&token.value.kind()) || token.value == Token::Error) {
+        return Err(unexpected(tokens, [&FACT_START[..], &[TokenKind::RParen]].concat(), context));
+    }
+
+    loop {
+        arguments.push(parse_expr(tokens, context)?);
+
+        match tokens.next_if(|token| matches!(token.value, Token::Comma | Token::RParen)) {
+            Some(Spanned { value: Token::Comma, .. }) => (),
+            Some(token) => return Ok((arguments, token.span)),
+            None => return Err(unexpected(tokens, [&[TokenKind::Comma, TokenKind::RParen], &BINARY_OPERATORS[..]].concat(), context)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    type Tokens<'src> = Peekable<Lexer<'src>>;
+
+    // The layered recursive descent parser that the operator table replaced, one function per grammar level.
+
+    fn reference_expr(tokens: &mut Tokens) -> Expr {
+        let mut expr = Expr::Sent(reference_sent(tokens));
+
+        loop {
+            let build: fn(Box<Expr>, Sent, Span) -> Expr = match tokens.peek().map(|token| &token.value) {
+                Some(Token::And) => Expr::And,
+                Some(Token::Or) => Expr::Or,
+                _ => return expr,
+            };
+
+            tokens.next();
+            let sent = reference_sent(tokens);
+            let span = expr.span().to(sent.span());
+            expr = build(Box::new(expr), sent, span);
+        }
+    }
+
+    fn reference_sent(tokens: &mut Tokens) -> Sent {
+        let mut sent = Sent::Comp(reference_comp(tokens));
+
+        loop {
+            let build: fn(Box<Sent>, Comp, Span) -> Sent = match tokens.peek().map(|token| &token.value) {
+                Some(Token::Equals) => Sent::Equals,
+                Some(Token::Greater) => Sent::Greater,
+                Some(Token::Less) => Sent::Less,
+                Some(Token::NotEquals) => Sent::NotEquals,
+                Some(Token::GreaterEquals) => Sent::GreaterEquals,
+                Some(Token::LessEquals) => Sent::LessEquals,
+                _ => return sent,
+            };
+
+            tokens.next();
+            let comp = reference_comp(tokens);
+            let span = sent.span().to(comp.span());
+            sent = build(Box::new(sent), comp, span);
+        }
+    }
+
+    fn reference_comp(tokens: &mut Tokens) -> Comp {
+        let mut comp = Comp::Term(reference_term(tokens));
+
+        loop {
+            let build: fn(Box<Comp>, Term, Span) -> Comp = match tokens.peek().map(|token| &token.value) {
+                Some(Token::Add) => Comp::Add,
+                Some(Token::Sub) => Comp::Sub,
+                Some(Token::BitOr) => Comp::BitOr,
+                Some(Token::Xor) => Comp::Xor,
+                _ => return comp,
+            };
+
+            tokens.next();
+            let term = reference_term(tokens);
+            let span = comp.span().to(term.span());
+            comp = build(Box::new(comp), term, span);
+        }
+    }
+
+    fn reference_term(tokens: &mut Tokens) -> Term {
+        let mut term = Term::Fact(reference_fact(tokens));
+
+        loop {
+            let build: fn(Box<Term>, Fact, Span) -> Term = match tokens.peek().map(|token| &token.value) {
+                Some(Token::Mul) => Term::Mul,
+                Some(Token::Div) => Term::Div,
+                Some(Token::Mod) => Term::Mod,
+                Some(Token::BitAnd) => Term::BitAnd,
+                Some(Token::Shl) => Term::Shl,
+                Some(Token::Shr) => Term::Shr,
+                _ => return term,
+            };
+
+            tokens.next();
+            let fact = reference_fact(tokens);
+            let span = term.span().to(fact.span());
+            term = build(Box::new(term), fact, span);
+        }
+    }
+
+    fn reference_fact(tokens: &mut Tokens) -> Fact {
+        let token = tokens.next().expect("the corpus only holds complete expressions");
+
+        let build: fn(Box<Fact>, Span) -> Fact = match token.value {
+            Token::Is => Fact::Is,
+            Token::Not => Fact::Not,
+            Token::Add => Fact::Pos,
+            Token::BitNot => Fact::BitNot,
+            Token::Sub => match tokens.peek() {
+                Some(Spanned { value: Token::Constant(constant), span }) if i32::try_from(-constant).is_ok() => {
+                    let prim = Prim::Constant(-*constant as i32, token.span.to(*span));
+                    tokens.next();
+                    return Fact::Prim(prim);
+                },
+                _ => Fact::Neg,
+            },
+            Token::LParen => {
+                let expr = reference_expr(tokens);
+                let end = tokens.next().expect("unclosed parenthesis in the corpus");
+                assert_eq!(end.value, Token::RParen);
+                return Fact::Prim(Prim::Expr(Box::new(expr), token.span.to(end.span)));
+            },
+            Token::Constant(constant) => return Fact::Prim(Prim::Constant(constant as i32, token.span)),
+            Token::Variable(variable) => return Fact::Prim(Prim::Variable(variable.to_string(), token.span)),
+            token => panic!("unexpected token in the corpus: {token}"),
+        };
+
+        let fact = reference_fact(tokens);
+        let span = token.span.to(fact.span());
+        build(Box::new(fact), span)
+    }
+
+    fn parse(source: &str) -> Expr {
+        let mut tokens = Lexer::new(source).peekable();
+        let expr = parse_expr(&mut tokens, Construct::Statement).unwrap();
+        assert!(tokens.next().is_none(), "{source}");
+
+        expr
+    }
+
+    fn assert_same_as_reference(source: &str) {
+        let mut tokens = Lexer::new(source).peekable();
+        let expected = reference_expr(&mut tokens);
+        assert!(tokens.next().is_none(), "{source}");
+
+        assert_eq!(parse(source), expected, "{source}");
+    }
+
+    const BINARY: [&str; 18] = ["&", "|", "=", ">", "<", "!=", ">=", "<=", "+", "-", ".|", "^", "*", "/", "%", ".&", "<<", ">>"];
+    const UNARY: [&str; 5] = ["?", "!", "+", "-", "~"];
+
+    // Deterministic xorshift, so every run checks the same corpus.
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    // Also returns whether the operand is i32::MIN, which may only be wrapped in parentheses and unary plus.
+    fn random_operand(random: &mut Random, depth: usize) -> (String, bool) {
+        let (operand, mut is_min) = match random.below(if depth > 0 { 4 } else { 3 }) {
+            0 => (random.below(100).to_string(), false),
+            1 => {
+                let operand = ["x", "y", "_z", "-2147483648"][random.below(4)];
+                (operand.to_string(), operand == "-2147483648")
+            },
+            2 => (["a", "b1", "0x7fff_ffff", "0b101"][random.below(4)].to_string(), false),
+            _ => {
+                let (expr, is_min) = random_expr(random, depth - 1);
+                (format!("({expr})"), is_min)
+            },
+        };
+
+        let mut prefix = vec![];
+
+        for _ in 0..random.below(3) {
+            let unary = match UNARY[random.below(UNARY.len())] {
+                "-" if is_min => "+",
+                unary => unary,
+            };
+            is_min &= unary == "+";
+            prefix.push(unary);
+        }
+
+        prefix.reverse();
+
+        (format!("{}{operand}", prefix.join(" ")), is_min)
+    }
+
+    fn random_expr(random: &mut Random, depth: usize) -> (String, bool) {
+        let (mut expr, mut is_min) = random_operand(random, depth);
+
+        for _ in 0..random.below(6) {
+            let space = [" ", "", "  "][random.below(3)];
+            expr = format!("{expr}{space}{}{space}{}", BINARY[random.below(BINARY.len())], random_operand(random, depth).0);
+            is_min = false;
+        }
+
+        (expr, is_min)
+    }
+
+    #[test]
+    fn operators_follow_the_readme_precedence() {
+        let Expr::Sent(Sent::Comp(Comp::Add(_, Term::Mul(..), span))) = parse("1 + 2 * 3") else {
+            panic!("`*` should bind tighter than `+`");
+        };
+        assert_eq!((span.start, span.end), (0, 9));
+
+        let Expr::And(_, Sent::Equals(_, Comp::Term(Term::BitAnd(..)), _), _) = parse("x & y = x .& 1") else {
+            panic!("`.&` should bind tighter than `=`, which binds tighter than `&`");
+        };
+
+        let Expr::Sent(Sent::Comp(Comp::Sub(left, _, _))) = parse("1 - 2 - 3") else {
+            panic!("`-` should be left associative");
+        };
+        assert!(matches!(*left, Comp::Sub(..)));
+    }
+
+    #[test]
+    fn negating_i32_min_is_too_large() {
+        for source in ["-0x8000_0000", "- -2147483648", "--2147483648", "-(-2147483648)", "-(0x8000_0000)", "-+(+(-2147483648))"] {
+            let mut tokens = Lexer::new(source).peekable();
+            let Err(ParserError::IntegerTooLarge(2_147_483_648, span)) = parse_expr(&mut tokens, Construct::Statement) else {
+                panic!("{source} should be too large");
+            };
+            assert_eq!((span.start, span.end), (0, source.len()), "{source}");
+        }
+    }
+
+    #[test]
+    fn matches_reference_parser_on_fixed_corpus() {
+        let corpus = [
+            "1", "-2147483648", "- 2147483648", "--1", "-(1)", "!?-x", "~~x",
+            "a & b | c & d", "1 = 2 < 3 != 4", "1 + 2 * 3 - 4 / 5 % 6",
+            "x .& 1 = 0", "x .| y ^ z .& w", "1 << 2 + 3 >> 4", "a < b & b <= c | !(c >= d)",
+            "x<9>6=y+z*y-(+6-!?-x*5)=--!x", "((((1))))", "(1 + 2) * (3 - -4)",
+        ];
+
+        for source in corpus {
+            assert_same_as_reference(source);
+        }
+    }
+
+    #[test]
+    fn matches_reference_parser_on_generated_corpus() {
+        let mut random = Random(0x2545_f491_4f6c_dd1d);
+
+        for _ in 0..2000 {
+            assert_same_as_reference(&random_expr(&mut random, 3).0);
+        }
+    }
+
+    fn parse_with_errors(source: &str) -> (Vec<Statement>, Vec<String>) {
+        let (program, errors) = parse_program(Lexer::new(source));
+
+        (program.block.statements, errors.iter().map(|error| error.to_string()).collect())
+    }
+
+    fn error_span(statement: &Statement) -> (usize, usize) {
+        let Statement::Error(span) = statement else {
+            panic!("{statement:?} should be an error");
+        };
+
+        (span.start, span.end)
+    }
+
+    #[test]
+    fn recovers_after_each_failed_statement() {
+        let source = "let x be 5 5;\nprint ;\nset x to 1;\nlet 3 be y;\nlet y be 1\nprint x;";
+        let (statements, errors) = parse_with_errors(source);
+
+        assert_eq!(errors, [
+            "1:12: unexpected token: constant 5 in `let` statement, expected one of: ';', operator",
+            "2:7: unexpected token: ';' in `print` statement, expected one of: identifier, constant, '(', '+', '-', '!', '?', '~', string",
+            "4:5: unexpected token: constant 3 in `let` statement, expected: identifier",
+            "6:1: unexpected token: 'print' in `let` statement, expected one of: ';', operator",
+        ]);
+
+        assert_eq!(statements.len(), 6);
+        assert_eq!(error_span(&statements[0]), (0, 13));
+        assert_eq!(error_span(&statements[1]), (14, 21));
+        assert!(matches!(statements[2], Statement::SetTo(_)));
+        assert_eq!(error_span(&statements[3]), (34, 45));
+        // Nothing is skipped before the next statement keyword, so the error only covers the `let`.
+        assert_eq!(error_span(&statements[4]), (46, 49));
+        assert!(matches!(statements[5], Statement::Print(_)));
+    }
+
+    #[test]
+    fn recovers_inside_blocks_and_skips_nested_blocks_whole() {
+        let source = "rep 2 { print 1 +; print 2; }\nwhile { print 3; } print 4;";
+        let (statements, errors) = parse_with_errors(source);
+
+        assert_eq!(errors, [
+            "1:18: unexpected token: ';' in `print` statement, expected one of: identifier, constant, '(', '+', '-', '!', '?', '~'",
+            "2:7: unexpected token: '{' in `while` statement, expected one of: identifier, constant, '(', '+', '-', '!', '?', '~'",
+        ]);
+
+        let [Statement::Rep(rep), while_, Statement::Print(_)] = statements.as_slice() else {
+            panic!("{statements:?}");
+        };
+        let [print, Statement::Print(_)] = rep.block.statements.as_slice() else {
+            panic!("{:?}", rep.block.statements);
+        };
+        assert_eq!(error_span(print), (8, 18));
+        assert_eq!(error_span(while_), (30, 48));
+    }
+
+    #[test]
+    fn reports_where_the_input_ends_early() {
+        let (statements, errors) = parse_with_errors("print 1;\nrep 2 { print 2;");
+
+        assert_eq!(errors, [
+            "2:16: unexpected end in `rep` statement, expected one of: 'let', 'set', 'rep', 'while', 'break', 'continue', 'fn', 'return', 'print', 'read', 'eat', '}'",
+        ]);
+        // The block ran to the end of the input, so there is nothing left to skip past the `rep`.
+        assert_eq!(error_span(&statements[1]), (9, 12));
+
+        let (_, errors) = parse_with_errors("let x be");
+        assert_eq!(errors, ["1:7: unexpected end in `let` statement, expected one of: identifier, constant, '(', '+', '-', '!', '?', '~', '['"]);
+    }
+}
@@ -1,16 +1,71 @@
-use crate::ast::*;
+use crate::{ast::*, mangle::mangle};
 
-pub fn transpile_program_to_rust(program: &Program, ) -> String {
+const RESERVED: &[&str] = &[
+    "_", "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern", "false",
+    "fn", "for", "gen", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub", "ref", "return",
+    "self", "Self", "static", "struct", "super", "trait", "true", "type", "unsafe", "use", "where", "while",
+    "abstract", "become", "box", "do", "final", "macro", "override", "priv", "try", "typeof", "unsized",
+    "virtual", "yield",
+    "Some", "None", "Ok", "Err",
+    "shift", "input", "bound",
+];
+
+const SHIFT: &str = "\
+#[allow(dead_code)]
+fn shift(x: i32, y: i64) -> i32 {
+    match y {
+        32.. => 0,
+        ..=-32 => -((x < 0) as i32),
+        0.. => ((x as u32) << y) as i32,
+        _ => x >> -y,
+    }
+}";
+
+const INPUT: &str = "\
+#[allow(dead_code)]
+fn input() -> i32 {
+    use std::io::BufRead;
+
+    let mut line = Vec::new();
+
+    if let Ok(0) | Err(_) = std::io::stdin().lock().read_until(b'\\n', &mut line) {
+        eprintln!(\"runtime error: unexpected end of input\");
+        std::process::exit(1);
+    }
+
+    let line = std::str::from_utf8(&line).unwrap_or_default().trim_matches(|c: char| c.is_ascii_whitespace());
+
+    match line.parse() {
+        Ok(value) => value,
+        _ => {
+            eprintln!(\"runtime error: expected an integer\");
+            std::process::exit(1);
+        },
+    }
+}";
+
+const BOUND: &str = "\
+#[allow(dead_code)]
+fn bound(index: i32, length: usize) -> usize {
+    if index < 0 || index as usize >= length {
+        eprintln!(\"runtime error: index {index} out of bounds for length {length}\");
+        std::process::exit(1);
+    }
+
+    index as usize
+}";
+
+pub fn transpile_program_to_rust(program: &Program) -> String {
     let rust_program = transpile_block(&program.block);
 
-    format!("fn main() {rust_program}")
+    format!("{SHIFT}\n\n{INPUT}\n\n{BOUND}\n\nfn main() {rust_program}")
 }
 
 fn transpile_block(block: &Block) -> String {
     let mut rust_block = String::new();
 
     for statement in &block.statements {
-        rust_block.push_str(&transpile_statement(&statement));
+        rust_block.push_str(&transpile_statement(statement));
     }
 
     format!("{{ {rust_block} }}")
@@ -19,76 +74,188 @@ fn transpile_block(block: &Block) -> String {
 fn transpile_statement(statement: &Statement) -> String {
     match statement {
         Statement::LetBe(let_be) => 
-            format!("let mut {} = {}; ", let_be.variable, transpile_expr(&let_be.expr)),
+            format!("let mut {} = {}; ", mangle(&let_be.variable, RESERVED), transpile_expr(&let_be.expr)),
+        Statement::LetBeArray(let_be_array) =>
+            format!("let mut {} = vec![0i32; {}]; ", mangle(&let_be_array.variable, RESERVED), let_be_array.size),
         Statement::SetTo(set_to) => 
-            format!("{} = {}; ", set_to.variable, transpile_expr(&set_to.expr)),
-        Statement::Rep(rep) => 
-            format!("for _ in 0..({} as i32).abs() {} ", transpile_expr(&rep.expr), transpile_block(&rep.block)),
-        Statement::Print(print) => 
-            format!("println!(\"{{}}\", {}); ", transpile_expr(&print.expr)),
+            format!("{} = {}; ", mangle(&set_to.variable, RESERVED), transpile_expr(&set_to.expr)),
+        Statement::SetIndexTo(set_index_to) => transpile_set_index_to(set_index_to),
+        Statement::Rep(rep) => transpile_rep(rep),
+        Statement::While(while_) =>
+            format!("while ({}) != 0 {} ", transpile_expr(&while_.expr), transpile_block(&while_.block)),
+        Statement::Break(_) => "break; ".to_string(),
+        Statement::Function(function) => transpile_function(function),
+        Statement::Return(return_) => format!("return {}; ", transpile_expr(&return_.expr)),
+        Statement::Continue(_) => "continue; ".to_string(),
+        Statement::Print(print) => transpile_print(print),
+        Statement::Read(read) => format!("{} = input(); ", mangle(&read.variable, RESERVED)),
+        // A later `let` shadows the variable, so there is nothing to do.
+        Statement::Eat(_) => String::new(),
+        Statement::Error(_) => unreachable!("programs with syntax errors are not transpiled"),
+    }
+}
+
+fn transpile_rep(rep: &Rep) -> String {
+    let counter = match &rep.counter {
+        Some(counter) => mangle(counter, RESERVED),
+        None => "_".into(),
+    };
+
+    let mut rust_rep = format!("{{ let m__n = ({} as i32).abs(); for {counter} in 0..m__n {} ", transpile_expr(&rep.expr), transpile_block(&rep.block));
+
+    if let Some(else_block) = &rep.else_block {
+        rust_rep.push_str(&format!("if m__n == 0 {} ", transpile_block(else_block)));
+    }
+
+    rust_rep.push_str("} ");
+
+    rust_rep
+}
+
+fn transpile_set_index_to(set_index_to: &SetIndexTo) -> String {
+    let array = mangle(&set_index_to.variable, RESERVED);
+
+    format!("{{ let m__k = {}; {array}[m__k] = {}; }} ", transpile_index(&array, &set_index_to.index), transpile_expr(&set_index_to.expr))
+}
+
+fn transpile_index(array: &str, index: &Expr) -> String {
+    format!("bound({}, {array}.len())", transpile_expr(index))
+}
+
+// Nested in main, where Rust makes functions visible to the whole block.
+fn transpile_function(function: &Function) -> String {
+    let parameters: Vec<String> = function.parameters.iter()
+        .map(|parameter| format!("mut {}: i32", mangle(parameter, RESERVED)))
+        .collect();
+
+    format!("fn {}({}) -> i32 {{ {} 0 }} ", mangle(&function.name, RESERVED), parameters.join(", "), transpile_block(&function.block))
+}
+
+fn transpile_print(print: &Print) -> String {
+    let mut format = String::new();
+    let mut arguments = String::new();
+
+    for item in &print.items {
+        match item {
+            PrintItem::Str(string, _) => format.push_str(&escape_string(&string.replace('{', "{{").replace('}', "}}"))),
+            PrintItem::Expr(expr) => {
+                format.push_str("{}");
+                arguments.push_str(&format!(", {}", transpile_expr(expr)));
+            },
+        }
+    }
+
+    format!("println!(\"{format}\"{arguments}); ")
+}
+
+fn escape_string(string: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in string.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\t' => escaped.push_str("\\t"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => escaped.push(c),
+        }
     }
+
+    escaped
 }
 
 fn transpile_expr(expr: &Expr) -> String {
     match expr {
-        Expr::And(expr, sent) => 
-            format!("(({} != 0 && {} != 0) as i32)", transpile_expr(&expr), transpile_sent(&sent)),
-        Expr::Or(expr, sent) =>
-            format!("(({} != 0 || {} != 0) as i32)", transpile_expr(&expr), transpile_sent(&sent)),
+        Expr::And(expr, sent, _) => 
+            format!("(({} != 0 && {} != 0) as i32)", transpile_expr(expr), transpile_sent(sent)),
+        Expr::Or(expr, sent, _) =>
+            format!("(({} != 0 || {} != 0) as i32)", transpile_expr(expr), transpile_sent(sent)),
         Expr::Sent(sent) => 
-            format!("{}", transpile_sent(&sent)),
+            transpile_sent(sent),
     }
 }
 
 fn transpile_sent(sent: &Sent) -> String {
     match sent {
-        Sent::Equals(sent, comp) => 
-            format!("(({} == {}) as i32)", transpile_sent(&sent), transpile_comp(&comp)),
-        Sent::Greater(sent, comp) => 
-            format!("(({} > {}) as i32)", transpile_sent(&sent), transpile_comp(&comp)),
-        Sent::Less(sent, comp) => 
-            format!("(({} < {}) as i32)", transpile_sent(&sent), transpile_comp(&comp)),
+        Sent::Equals(sent, comp, _) => 
+            format!("(({} == {}) as i32)", transpile_sent(sent), transpile_comp(comp)),
+        Sent::Greater(sent, comp, _) => 
+            format!("(({} > {}) as i32)", transpile_sent(sent), transpile_comp(comp)),
+        Sent::Less(sent, comp, _) => 
+            format!("(({} < {}) as i32)", transpile_sent(sent), transpile_comp(comp)),
+        Sent::NotEquals(sent, comp, _) => 
+            format!("(({} != {}) as i32)", transpile_sent(sent), transpile_comp(comp)),
+        Sent::GreaterEquals(sent, comp, _) => 
+            format!("(({} >= {}) as i32)", transpile_sent(sent), transpile_comp(comp)),
+        Sent::LessEquals(sent, comp, _) => 
+            format!("(({} <= {}) as i32)", transpile_sent(sent), transpile_comp(comp)),
         Sent::Comp(comp) =>
-            format!("{}", transpile_comp(&comp)),
+            transpile_comp(comp),
     }
 }
 
 fn transpile_comp(comp: &Comp) -> String {
     match comp {
-        Comp::Add(comp, term) => 
-            format!("({} + {})", transpile_comp(&comp), transpile_term(&term)),
-        Comp::Sub(comp, term) => 
-            format!("({} - {})", transpile_comp(&comp), transpile_term(&term)),
+        Comp::Add(comp, term, _) => 
+            format!("({} + {})", transpile_comp(comp), transpile_term(term)),
+        Comp::Sub(comp, term, _) => 
+            format!("({} - {})", transpile_comp(comp), transpile_term(term)),
+        Comp::BitOr(comp, term, _) =>
+            format!("({} | {})", transpile_comp(comp), transpile_term(term)),
+        Comp::Xor(comp, term, _) =>
+            format!("({} ^ {})", transpile_comp(comp), transpile_term(term)),
         Comp::Term(term) =>
-            format!("{}", transpile_term(&term)),
+            transpile_term(term),
     }
 }
 
 fn transpile_term(term: &Term) -> String {
     match term {
-        Term::Mul(term, fact) =>
-        format!("({} * {})", transpile_term(&term), transpile_fact(&fact)),
-        Term::Div(term, fact) =>
-        format!("({} / {})", transpile_term(&term), transpile_fact(&fact)),
+        Term::Mul(term, fact, _) =>
+        format!("({} * {})", transpile_term(term), transpile_fact(fact)),
+        Term::Div(term, fact, _) =>
+        format!("({} / {})", transpile_term(term), transpile_fact(fact)),
+        // Rust's % already truncates, wrapping only keeps i32::MIN % -1 from overflowing.
+        Term::Mod(term, fact, _) =>
+        format!("i32::wrapping_rem({}, {})", transpile_term(term), transpile_fact(fact)),
+        Term::BitAnd(term, fact, _) =>
+        format!("({} & {})", transpile_term(term), transpile_fact(fact)),
+        Term::Shl(term, fact, _) =>
+        format!("shift({}, i64::from({}))", transpile_term(term), transpile_fact(fact)),
+        Term::Shr(term, fact, _) =>
+        format!("shift({}, -i64::from({}))", transpile_term(term), transpile_fact(fact)),
         Term::Fact(fact) =>
-        format!("{}", transpile_fact(&fact)),
+        transpile_fact(fact),
     }
 }
 
 fn transpile_fact(fact: &Fact) -> String {
     match fact {
-        Fact::Is(prim) => format!("({} as i32).signum()", transpile_prim(prim)),
-        Fact::Not(prim) => format!("(!{} as i32)", transpile_prim(prim)),
-        Fact::Pos(prim) => format!("{}", transpile_prim(prim)),
-        Fact::Neg(prim) => format!("(-{})", transpile_prim(prim)),
-        Fact::Prim(prim) => format!("{}", transpile_prim(prim)),
+        Fact::Is(fact, _) => format!("({} as i32).signum()", transpile_fact(fact)),
+        Fact::Not(fact, _) => format!("(({} == 0) as i32)", transpile_fact(fact)),
+        Fact::Pos(fact, _) => transpile_fact(fact),
+        Fact::Neg(fact, _) => format!("(-{})", transpile_fact(fact)),
+        Fact::BitNot(fact, _) => format!("(!{})", transpile_fact(fact)),
+        Fact::Prim(prim) => transpile_prim(prim),
     }
 }
 
 fn transpile_prim(prim: &Prim) -> String {
     match prim {
-        Prim::Expr(expr) => format!("({})", transpile_expr(&expr)),
-        Prim::Constant(constant) => format!("{constant}"),
-        Prim::Variable(variable) => format!("{variable}"),
+        Prim::Expr(expr, _) => format!("({})", transpile_expr(expr)),
+        Prim::Constant(constant, _) if *constant < 0 => format!("({constant})"),
+        Prim::Constant(constant, _) => format!("{constant}"),
+        Prim::Variable(variable, _) => mangle(variable, RESERVED).into_owned(),
+        Prim::Call(function, arguments, _) => {
+            let arguments: Vec<String> = arguments.iter().map(transpile_expr).collect();
+
+            format!("{}({})", mangle(function, RESERVED), arguments.join(", "))
+        },
+        Prim::Index(array, index, _) => {
+            let array = mangle(array, RESERVED);
+
+            format!("{array}[{}]", transpile_index(&array, index))
+        },
     }
 }
\ No newline at end of file
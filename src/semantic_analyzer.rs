@@ -1,175 +1,566 @@
 // Scope: variables have lifetime scope.
-// Lifetime: variables live from declaration to program end unless explicitly destroyed.
+// Lifetime: variables live from declaration to program end unless explicitly destroyed with `eat`.
 
-use std::{collections::HashSet, fmt::Display, borrow::Cow};
-use crate::ast::*;
+use std::{collections::HashMap, fmt::Display, borrow::Cow};
+use crate::{ast::*, span::Span};
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum SemanticAnalyzerError {
-    RedeclaredVariable(String),
-    UndeclaredVariable(String),
-    DeclaredLocalVariable(String), // temporary solution to scope: everythin must be a global
+    RedeclaredVariable(String, Span),
+    UndeclaredVariable(String, Span),
+    DeclaredLocalVariable(String, Span), // temporary solution to scope: everythin must be a global
+    BreakOutsideLoop(Span),
+    ContinueOutsideLoop(Span),
+    AssignedLoopCounter(String, Span),
+    RedeclaredFunction(String, Span),
+    UndeclaredFunction(String, Span),
+    NestedFunction(String, Span),
+    // Function, expected and received argument count.
+    ArgumentCount(String, usize, usize, Span),
+    ReturnOutsideFunction(Span),
+    NestedEat(String, Span),
+    InvalidArraySize(String, i32, Span),
+    IndexedScalar(String, Span),
+    UnindexedArray(String, Span),
+    // What expected the type, such as an operator, then the expected and received type.
+    TypeMismatch(String, Type, Type, Span),
 }
 
 impl Display for SemanticAnalyzerError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let output: Cow<str> = match self {
-            SemanticAnalyzerError::RedeclaredVariable(variable) => 
-                Cow::Owned(format!("redeclared variable: {variable}")),
-            SemanticAnalyzerError::UndeclaredVariable(variable) => 
-                Cow::Owned(format!("undeclared variable: {variable}")),
-            SemanticAnalyzerError::DeclaredLocalVariable(variable) => 
-                Cow::Owned(format!("declared local variable: {variable}")),
-
+            SemanticAnalyzerError::RedeclaredVariable(variable, span) =>
+                Cow::Owned(format!("{span}: redeclared variable: {variable}")),
+            SemanticAnalyzerError::UndeclaredVariable(variable, span) =>
+                Cow::Owned(format!("{span}: undeclared variable: {variable}")),
+            SemanticAnalyzerError::DeclaredLocalVariable(variable, span) =>
+                Cow::Owned(format!("{span}: declared local variable: {variable}")),
+            SemanticAnalyzerError::BreakOutsideLoop(span) =>
+                Cow::Owned(format!("{span}: break outside of a loop")),
+            SemanticAnalyzerError::ContinueOutsideLoop(span) =>
+                Cow::Owned(format!("{span}: continue outside of a loop")),
+            SemanticAnalyzerError::AssignedLoopCounter(variable, span) =>
+                Cow::Owned(format!("{span}: assigned read-only loop counter: {variable}")),
+            SemanticAnalyzerError::RedeclaredFunction(function, span) =>
+                Cow::Owned(format!("{span}: redeclared function: {function}")),
+            SemanticAnalyzerError::UndeclaredFunction(function, span) =>
+                Cow::Owned(format!("{span}: undeclared function: {function}")),
+            SemanticAnalyzerError::NestedFunction(function, span) =>
+                Cow::Owned(format!("{span}: function declared outside the global scope: {function}")),
+            SemanticAnalyzerError::ArgumentCount(function, expected, received, span) =>
+                Cow::Owned(format!("{span}: wrong number of arguments to {function}: expected {expected}, received {received}")),
+            SemanticAnalyzerError::ReturnOutsideFunction(span) =>
+                Cow::Owned(format!("{span}: return outside of a function")),
+            SemanticAnalyzerError::NestedEat(variable, span) =>
+                Cow::Owned(format!("{span}: ate variable outside the outermost block: {variable}")),
+            SemanticAnalyzerError::InvalidArraySize(array, size, span) =>
+                Cow::Owned(format!("{span}: array size must be between 1 and {MAX_ARRAY_SIZE}: {array} has size {size}")),
+            SemanticAnalyzerError::IndexedScalar(variable, span) =>
+                Cow::Owned(format!("{span}: indexed a variable that is not an array: {variable}")),
+            SemanticAnalyzerError::UnindexedArray(array, span) =>
+                Cow::Owned(format!("{span}: used an array without an index: {array}")),
+            SemanticAnalyzerError::TypeMismatch(what, expected, received, span) =>
+                Cow::Owned(format!("{span}: {what} expects {expected}, received {received}")),
         };
 
         write!(f, "{output}")
     }
 }
 
-pub fn validate_program(program: &Program) -> Result<(), SemanticAnalyzerError> {
-    let mut symbol_table = HashSet::new();
+impl std::error::Error for SemanticAnalyzerError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Type {
+    Int,
+    Bool,
+}
+
+impl Display for Type {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Type::Int => write!(f, "int"),
+            Type::Bool => write!(f, "bool"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Typing {
+    Lax,
+    Strict,
+}
+
+impl Typing {
+    fn expect(self, what: impl Display, expected: Type, received: Type, span: Span) -> Result<(), SemanticAnalyzerError> {
+        match self {
+            Typing::Strict if received != expected => Err(SemanticAnalyzerError::TypeMismatch(what.to_string(), expected, received, span)),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Symbol {
+    Variable(Type),
+    Counter,
+    Array,
+    Function(usize),
+}
+
+type SymbolTable = HashMap<String, Symbol>;
+
+// Only a sanity bound, as both backends keep arrays on the heap.
+const MAX_ARRAY_SIZE: i32 = 65_536;
+
+#[derive(Debug, Clone, Copy)]
+struct Scope {
+    is_top_level: bool,
+    is_in_loop: bool,
+    is_in_function: bool,
+}
+
+impl Scope {
+    fn looping(self) -> Scope {
+        Scope { is_top_level: false, is_in_loop: true, ..self }
+    }
+
+    fn nested(self) -> Scope {
+        Scope { is_top_level: false, ..self }
+    }
+}
+
+pub fn validate_program(program: &Program, typing: Typing) -> Result<(), SemanticAnalyzerError> {
+    let mut symbol_table = HashMap::new();
+
+    // Functions are collected first, so they may be called before their declaration and from themselves.
+    for statement in &program.block.statements {
+        if let Statement::Function(function) = statement {
+            if symbol_table.insert(function.name.to_owned(), Symbol::Function(function.parameters.len())).is_some() {
+                return Err(SemanticAnalyzerError::RedeclaredFunction(function.name.to_owned(), function.span));
+            }
+        }
+    }
 
-    validate_block(&program.block, &mut symbol_table, true)?;
+    validate_block(&program.block, &mut symbol_table, Scope { is_top_level: true, is_in_loop: false, is_in_function: false }, typing)?;
 
     Ok(())
 }
 
-fn validate_block(block: &Block, symbol_table: &mut HashSet<String>, is_global_scope: bool) -> Result<(), SemanticAnalyzerError> {
+fn validate_block(block: &Block, symbol_table: &mut SymbolTable, scope: Scope, typing: Typing) -> Result<(), SemanticAnalyzerError> {
     for statement in &block.statements {
-        validate_statement(&statement, symbol_table, is_global_scope)?;
+        validate_statement(statement, symbol_table, scope, typing)?;
     }
 
     Ok(())
 }
 
-fn validate_statement(statement: &Statement, symbol_table: &mut HashSet<String>, is_global_scope: bool) -> Result<(), SemanticAnalyzerError> {
+fn validate_statement(statement: &Statement, symbol_table: &mut SymbolTable, scope: Scope, typing: Typing) -> Result<(), SemanticAnalyzerError> {
     match statement {
-        Statement::LetBe(let_be) => validate_let_be(let_be, symbol_table, is_global_scope)?,
-        Statement::SetTo(set_to) => validate_set_to(set_to, symbol_table)?,
-        Statement::Rep(rep)        => validate_rep(rep, symbol_table)?,
-        Statement::Print(print)  => validate_print(print, symbol_table)?,
+        Statement::LetBe(let_be) => validate_let_be(let_be, symbol_table, scope, typing)?,
+        Statement::LetBeArray(let_be_array) => validate_let_be_array(let_be_array, symbol_table, scope)?,
+        Statement::SetTo(set_to) => validate_set_to(set_to, symbol_table, typing)?,
+        Statement::SetIndexTo(set_index_to) => validate_set_index_to(set_index_to, symbol_table, typing)?,
+        Statement::Rep(rep)        => validate_rep(rep, symbol_table, scope, typing)?,
+        Statement::While(while_) => validate_while(while_, symbol_table, scope, typing)?,
+        Statement::Break(span) if !scope.is_in_loop => return Err(SemanticAnalyzerError::BreakOutsideLoop(*span)),
+        Statement::Continue(span) if !scope.is_in_loop => return Err(SemanticAnalyzerError::ContinueOutsideLoop(*span)),
+        Statement::Break(_) | Statement::Continue(_) => (),
+        Statement::Function(function) => validate_function(function, symbol_table, scope, typing)?,
+        Statement::Return(return_) if !scope.is_in_function => return Err(SemanticAnalyzerError::ReturnOutsideFunction(return_.span)),
+        // Functions always return integers.
+        Statement::Return(return_) => {
+            let received = validate_expr(&return_.expr, symbol_table, typing)?;
+            typing.expect("`return` statement", Type::Int, received, return_.expr.span())?;
+        },
+        Statement::Print(print)  => validate_print(print, symbol_table, typing)?,
+        Statement::Read(read) => validate_read(read, symbol_table, typing)?,
+        Statement::Eat(eat) => validate_eat(eat, symbol_table, scope)?,
+        // Already reported by the parser.
+        Statement::Error(_) => (),
     }
 
     Ok(())
 }
 
-fn validate_let_be(let_be: &LetBe, symbol_table: &mut HashSet<String>, is_global_scope: bool) -> Result<(), SemanticAnalyzerError> {
-    if symbol_table.contains(&let_be.variable) {
-        return Err(SemanticAnalyzerError::RedeclaredVariable(let_be.variable.to_owned()));
+fn validate_let_be(let_be: &LetBe, symbol_table: &mut SymbolTable, scope: Scope, typing: Typing) -> Result<(), SemanticAnalyzerError> {
+    if symbol_table.contains_key(&let_be.variable) {
+        return Err(SemanticAnalyzerError::RedeclaredVariable(let_be.variable.to_owned(), let_be.span));
     }
 
-    if !is_global_scope {
-        return Err(SemanticAnalyzerError::DeclaredLocalVariable(let_be.variable.to_owned()));
+    if !scope.is_top_level {
+        return Err(SemanticAnalyzerError::DeclaredLocalVariable(let_be.variable.to_owned(), let_be.span));
     }
 
-    validate_expr(&let_be.expr, symbol_table)?;
+    let variable_type = validate_expr(&let_be.expr, symbol_table, typing)?;
 
-    symbol_table.insert(let_be.variable.to_owned());
+    symbol_table.insert(let_be.variable.to_owned(), Symbol::Variable(variable_type));
 
     Ok(())
 }
 
-fn validate_set_to(set_to: &SetTo, symbol_table: &mut HashSet<String>) -> Result<(), SemanticAnalyzerError> {
-    if !symbol_table.contains(&set_to.variable) {
-        return Err(SemanticAnalyzerError::UndeclaredVariable(set_to.variable.to_owned()));
+fn validate_let_be_array(let_be_array: &LetBeArray, symbol_table: &mut SymbolTable, scope: Scope) -> Result<(), SemanticAnalyzerError> {
+    if symbol_table.contains_key(&let_be_array.variable) {
+        return Err(SemanticAnalyzerError::RedeclaredVariable(let_be_array.variable.to_owned(), let_be_array.span));
+    }
+
+    if !scope.is_top_level {
+        return Err(SemanticAnalyzerError::DeclaredLocalVariable(let_be_array.variable.to_owned(), let_be_array.span));
+    }
+
+    if !(1..=MAX_ARRAY_SIZE).contains(&let_be_array.size) {
+        return Err(SemanticAnalyzerError::InvalidArraySize(let_be_array.variable.to_owned(), let_be_array.size, let_be_array.span));
     }
 
-    validate_expr(&set_to.expr, symbol_table)?;
+    symbol_table.insert(let_be_array.variable.to_owned(), Symbol::Array);
 
     Ok(())
 }
 
-fn validate_rep(rep: &Rep, symbol_table: &mut HashSet<String>) -> Result<(), SemanticAnalyzerError> {
-    validate_expr(&rep.expr, symbol_table)?;
+fn validate_set_to(set_to: &SetTo, symbol_table: &mut SymbolTable, typing: Typing) -> Result<(), SemanticAnalyzerError> {
+    let expected = match symbol_table.get(&set_to.variable) {
+        Some(Symbol::Variable(variable_type)) => *variable_type,
+        Some(Symbol::Counter) => return Err(SemanticAnalyzerError::AssignedLoopCounter(set_to.variable.to_owned(), set_to.span)),
+        Some(Symbol::Array) => return Err(SemanticAnalyzerError::UnindexedArray(set_to.variable.to_owned(), set_to.span)),
+        Some(Symbol::Function(_)) | None => return Err(SemanticAnalyzerError::UndeclaredVariable(set_to.variable.to_owned(), set_to.span)),
+    };
 
-    validate_block(&rep.block, symbol_table, false)?;
+    let received = validate_expr(&set_to.expr, symbol_table, typing)?;
+    typing.expect("`set` statement", expected, received, set_to.expr.span())?;
 
     Ok(())
 }
 
-fn validate_print(print: &Print, symbol_table: &mut HashSet<String>) -> Result<(), SemanticAnalyzerError> {
-    validate_expr(&print.expr, symbol_table)?;
+fn validate_set_index_to(set_index_to: &SetIndexTo, symbol_table: &mut SymbolTable, typing: Typing) -> Result<(), SemanticAnalyzerError> {
+    match symbol_table.get(&set_index_to.variable) {
+        Some(Symbol::Array) => (),
+        Some(Symbol::Variable(_) | Symbol::Counter) => return Err(SemanticAnalyzerError::IndexedScalar(set_index_to.variable.to_owned(), set_index_to.span)),
+        Some(Symbol::Function(_)) | None => return Err(SemanticAnalyzerError::UndeclaredVariable(set_index_to.variable.to_owned(), set_index_to.span)),
+    }
+
+    let received = validate_expr(&set_index_to.index, symbol_table, typing)?;
+    typing.expect("`[]`", Type::Int, received, set_index_to.index.span())?;
+
+    let received = validate_expr(&set_index_to.expr, symbol_table, typing)?;
+    typing.expect("`set` statement", Type::Int, received, set_index_to.expr.span())?;
 
     Ok(())
 }
 
-fn validate_expr(expr: &Expr, symbol_table: &mut HashSet<String>) -> Result<(), SemanticAnalyzerError> {
-    match expr {
-        Expr::And(expr, sent) | 
-        Expr::Or(expr, sent) => {
-            validate_expr(&expr, symbol_table)?;
-            validate_sent(&sent, symbol_table)?;
-        },
-        Expr::Sent(sent) => validate_sent(&sent, symbol_table)?,
+fn validate_read(read: &Read, symbol_table: &SymbolTable, typing: Typing) -> Result<(), SemanticAnalyzerError> {
+    match symbol_table.get(&read.variable) {
+        Some(Symbol::Variable(variable_type)) => typing.expect("`read` statement", Type::Int, *variable_type, read.span),
+        Some(Symbol::Counter) => Err(SemanticAnalyzerError::AssignedLoopCounter(read.variable.to_owned(), read.span)),
+        Some(Symbol::Array) => Err(SemanticAnalyzerError::UnindexedArray(read.variable.to_owned(), read.span)),
+        Some(Symbol::Function(_)) | None => Err(SemanticAnalyzerError::UndeclaredVariable(read.variable.to_owned(), read.span)),
     }
-
-    Ok(())
 }
 
-fn validate_sent(sent: &Sent, symbol_table: &mut HashSet<String>) -> Result<(), SemanticAnalyzerError> {
-    match sent {
-        Sent::Equals(sent, comp) |
-        Sent::Greater(sent, comp) |
-        Sent::Less(sent, comp) => {
-            validate_sent(&sent, symbol_table)?;
-            validate_comp(&comp, symbol_table)?;
-        },
-        Sent::Comp(comp) => validate_comp(&comp, symbol_table)?,
+// Like `let`, `eat` is only allowed in the outermost block, so whether a variable is alive never depends on a loop.
+fn validate_eat(eat: &Eat, symbol_table: &mut SymbolTable, scope: Scope) -> Result<(), SemanticAnalyzerError> {
+    if !scope.is_top_level {
+        return Err(SemanticAnalyzerError::NestedEat(eat.variable.to_owned(), eat.span));
     }
 
+    match symbol_table.get(&eat.variable) {
+        Some(Symbol::Variable(_) | Symbol::Array) => symbol_table.remove(&eat.variable),
+        _ => return Err(SemanticAnalyzerError::UndeclaredVariable(eat.variable.to_owned(), eat.span)),
+    };
+
     Ok(())
 }
 
-fn validate_comp(comp: &Comp, symbol_table: &mut HashSet<String>) -> Result<(), SemanticAnalyzerError> {
-    match comp {
-        Comp::Add(comp, term) |
-        Comp::Sub(comp, term) => {
-            validate_comp(&comp, symbol_table)?;
-            validate_term(&term, symbol_table)?;
-        },
-        Comp::Term(term) => validate_term(&term, symbol_table)?,
+fn validate_rep(rep: &Rep, symbol_table: &mut SymbolTable, scope: Scope, typing: Typing) -> Result<(), SemanticAnalyzerError> {
+    validate_expr(&rep.expr, symbol_table, typing)?;
+
+    if let Some(counter) = &rep.counter {
+        if symbol_table.contains_key(counter) {
+            return Err(SemanticAnalyzerError::RedeclaredVariable(counter.to_owned(), rep.span));
+        }
+
+        symbol_table.insert(counter.to_owned(), Symbol::Counter);
+    }
+
+    validate_block(&rep.block, symbol_table, scope.looping(), typing)?;
+
+    if let Some(counter) = &rep.counter {
+        symbol_table.remove(counter);
+    }
+
+    // The else block is not part of the loop, so `break` and `continue` there refer to an outer one.
+    if let Some(else_block) = &rep.else_block {
+        validate_block(else_block, symbol_table, scope.nested(), typing)?;
     }
 
     Ok(())
 }
 
-fn validate_term(term: &Term, symbol_table: &mut HashSet<String>) -> Result<(), SemanticAnalyzerError> {
-    match term {
-        Term::Mul(term, fact) |
-        Term::Div(term, fact) => {
-            validate_term(&term, symbol_table)?;
-            validate_fact(&fact, symbol_table)?;
-        },
-        Term::Fact(fact) => validate_fact(&fact, symbol_table)?,
-    }
+fn validate_while(while_: &While, symbol_table: &mut SymbolTable, scope: Scope, typing: Typing) -> Result<(), SemanticAnalyzerError> {
+    let received = validate_expr(&while_.expr, symbol_table, typing)?;
+    typing.expect("`while` statement", Type::Bool, received, while_.expr.span())?;
+
+    validate_block(&while_.block, symbol_table, scope.looping(), typing)?;
 
     Ok(())
 }
 
-fn validate_fact(fact: &Fact, symbol_table: &mut HashSet<String>) -> Result<(), SemanticAnalyzerError> {
-    match fact {
-        Fact::Is(prim) |
-        Fact::Not(prim) |
-        Fact::Pos(prim) |
-        Fact::Neg(prim) |
-        Fact::Prim(prim) => validate_prim(&prim, symbol_table)?,
+fn validate_function(function: &Function, symbol_table: &SymbolTable, scope: Scope, typing: Typing) -> Result<(), SemanticAnalyzerError> {
+    if !scope.is_top_level || scope.is_in_function {
+        return Err(SemanticAnalyzerError::NestedFunction(function.name.to_owned(), function.span));
+    }
+
+    // The body sees every function, but none of the global variables.
+    let mut function_table: SymbolTable = symbol_table.iter()
+        .filter(|(_, symbol)| matches!(symbol, Symbol::Function(_)))
+        .map(|(name, symbol)| (name.to_owned(), *symbol))
+        .collect();
+
+    for parameter in &function.parameters {
+        if function_table.contains_key(parameter) {
+            return Err(SemanticAnalyzerError::RedeclaredVariable(parameter.to_owned(), function.span));
+        }
+
+        function_table.insert(parameter.to_owned(), Symbol::Variable(Type::Int));
     }
 
+    validate_block(&function.block, &mut function_table, Scope { is_top_level: true, is_in_loop: false, is_in_function: true }, typing)?;
+
     Ok(())
 }
 
-fn validate_prim(prim: &Prim, symbol_table: &mut HashSet<String>) -> Result<(), SemanticAnalyzerError> {
-    match prim {
-        Prim::Expr(expr) => validate_expr(&expr, symbol_table)?,
-        Prim::Constant(_) => (),
-        Prim::Variable(variable) => {
-            if !symbol_table.contains(variable) {
-                return Err(SemanticAnalyzerError::UndeclaredVariable(variable.to_owned()));
-            }
+fn validate_print(print: &Print, symbol_table: &mut SymbolTable, typing: Typing) -> Result<(), SemanticAnalyzerError> {
+    for item in &print.items {
+        match item {
+            PrintItem::Str(..) => (),
+            PrintItem::Expr(expr) => { validate_expr(expr, symbol_table, typing)?; },
         }
     }
 
     Ok(())
 }
 
+fn validate_expr(expr: &Expr, symbol_table: &mut SymbolTable, typing: Typing) -> Result<Type, SemanticAnalyzerError> {
+    let (operator, expr, sent) = match expr {
+        Expr::And(expr, sent, _) => ("`&`", expr, sent),
+        Expr::Or(expr, sent, _) => ("`|`", expr, sent),
+        Expr::Sent(sent) => return validate_sent(sent, symbol_table, typing),
+    };
+
+    let received = validate_expr(expr, symbol_table, typing)?;
+    typing.expect(operator, Type::Bool, received, expr.span())?;
+
+    let received = validate_sent(sent, symbol_table, typing)?;
+    typing.expect(operator, Type::Bool, received, sent.span())?;
+
+    Ok(Type::Bool)
+}
+
+fn validate_sent(sent: &Sent, symbol_table: &mut SymbolTable, typing: Typing) -> Result<Type, SemanticAnalyzerError> {
+    let (operator, is_equality, left, right) = match sent {
+        Sent::Equals(sent, comp, _) => ("`=`", true, sent, comp),
+        Sent::NotEquals(sent, comp, _) => ("`!=`", true, sent, comp),
+        Sent::Greater(sent, comp, _) => ("`>`", false, sent, comp),
+        Sent::Less(sent, comp, _) => ("`<`", false, sent, comp),
+        Sent::GreaterEquals(sent, comp, _) => ("`>=`", false, sent, comp),
+        Sent::LessEquals(sent, comp, _) => ("`<=`", false, sent, comp),
+        Sent::Comp(comp) => return validate_comp(comp, symbol_table, typing),
+    };
+
+    let left_type = validate_sent(left, symbol_table, typing)?;
+    let right_type = validate_comp(right, symbol_table, typing)?;
+
+    if is_equality {
+        typing.expect(operator, left_type, right_type, right.span())?;
+    } else {
+        typing.expect(operator, Type::Int, left_type, left.span())?;
+        typing.expect(operator, Type::Int, right_type, right.span())?;
+    }
+
+    Ok(Type::Bool)
+}
+
+fn validate_comp(comp: &Comp, symbol_table: &mut SymbolTable, typing: Typing) -> Result<Type, SemanticAnalyzerError> {
+    let (operator, comp, term) = match comp {
+        Comp::Add(comp, term, _) => ("`+`", comp, term),
+        Comp::Sub(comp, term, _) => ("`-`", comp, term),
+        Comp::BitOr(comp, term, _) => ("`.|`", comp, term),
+        Comp::Xor(comp, term, _) => ("`^`", comp, term),
+        Comp::Term(term) => return validate_term(term, symbol_table, typing),
+    };
+
+    let received = validate_comp(comp, symbol_table, typing)?;
+    typing.expect(operator, Type::Int, received, comp.span())?;
+
+    let received = validate_term(term, symbol_table, typing)?;
+    typing.expect(operator, Type::Int, received, term.span())?;
+
+    Ok(Type::Int)
+}
+
+fn validate_term(term: &Term, symbol_table: &mut SymbolTable, typing: Typing) -> Result<Type, SemanticAnalyzerError> {
+    let (operator, term, fact) = match term {
+        Term::Mul(term, fact, _) => ("`*`", term, fact),
+        Term::Div(term, fact, _) => ("`/`", term, fact),
+        Term::Mod(term, fact, _) => ("`%`", term, fact),
+        Term::BitAnd(term, fact, _) => ("`.&`", term, fact),
+        Term::Shl(term, fact, _) => ("`<<`", term, fact),
+        Term::Shr(term, fact, _) => ("`>>`", term, fact),
+        Term::Fact(fact) => return validate_fact(fact, symbol_table, typing),
+    };
+
+    let received = validate_term(term, symbol_table, typing)?;
+    typing.expect(operator, Type::Int, received, term.span())?;
+
+    let received = validate_fact(fact, symbol_table, typing)?;
+    typing.expect(operator, Type::Int, received, fact.span())?;
+
+    Ok(Type::Int)
+}
+
+fn validate_fact(fact: &Fact, symbol_table: &mut SymbolTable, typing: Typing) -> Result<Type, SemanticAnalyzerError> {
+    // `!` is the only unary operator taking and giving a boolean.
+    let (operator, operand_type, fact) = match fact {
+        Fact::Not(fact, _) => ("`!`", Type::Bool, fact),
+        Fact::Is(fact, _) => ("`?`", Type::Int, fact),
+        Fact::Pos(fact, _) => ("`+`", Type::Int, fact),
+        Fact::Neg(fact, _) => ("`-`", Type::Int, fact),
+        Fact::BitNot(fact, _) => ("`~`", Type::Int, fact),
+        Fact::Prim(prim) => return validate_prim(prim, symbol_table, typing),
+    };
+
+    let received = validate_fact(fact, symbol_table, typing)?;
+    typing.expect(operator, operand_type, received, fact.span())?;
+
+    Ok(operand_type)
+}
+
+fn validate_prim(prim: &Prim, symbol_table: &mut SymbolTable, typing: Typing) -> Result<Type, SemanticAnalyzerError> {
+    let prim_type = match prim {
+        Prim::Expr(expr, _) => validate_expr(expr, symbol_table, typing)?,
+        Prim::Constant(..) => Type::Int,
+        Prim::Variable(variable, span) => match symbol_table.get(variable) {
+            Some(Symbol::Variable(variable_type)) => *variable_type,
+            Some(Symbol::Counter) => Type::Int,
+            Some(Symbol::Array) => return Err(SemanticAnalyzerError::UnindexedArray(variable.to_owned(), *span)),
+            Some(Symbol::Function(_)) | None => return Err(SemanticAnalyzerError::UndeclaredVariable(variable.to_owned(), *span)),
+        },
+        Prim::Call(function, arguments, span) => {
+            match symbol_table.get(function) {
+                Some(Symbol::Function(arity)) if *arity == arguments.len() => (),
+                Some(Symbol::Function(arity)) =>
+                    return Err(SemanticAnalyzerError::ArgumentCount(function.to_owned(), *arity, arguments.len(), *span)),
+                _ => return Err(SemanticAnalyzerError::UndeclaredFunction(function.to_owned(), *span)),
+            }
+
+            for argument in arguments {
+                let received = validate_expr(argument, symbol_table, typing)?;
+                typing.expect(format!("call to {function}"), Type::Int, received, argument.span())?;
+            }
+
+            Type::Int
+        },
+        Prim::Index(array, index, span) => {
+            match symbol_table.get(array) {
+                Some(Symbol::Array) => (),
+                Some(Symbol::Variable(_) | Symbol::Counter) => return Err(SemanticAnalyzerError::IndexedScalar(array.to_owned(), *span)),
+                Some(Symbol::Function(_)) | None => return Err(SemanticAnalyzerError::UndeclaredVariable(array.to_owned(), *span)),
+            }
+
+            let received = validate_expr(index, symbol_table, typing)?;
+            typing.expect("`[]`", Type::Int, received, index.span())?;
+
+            Type::Int
+        },
+    };
+
+    Ok(prim_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::Lexer, parser::parse_program};
+
+    fn validate(source: &str, typing: Typing) -> Result<(), String> {
+        let (program, errors) = parse_program(Lexer::new(source));
+        assert!(errors.is_empty(), "{source}: {errors:?}");
+
+        validate_program(&program, typing).map_err(|error| error.to_string())
+    }
+
+    #[test]
+    fn loop_counters_are_read_only_and_scoped_to_the_block() {
+        assert_eq!(validate("rep 3 as i { print i; }", Typing::Lax), Ok(()));
+        assert_eq!(validate("rep 3 as i { set i to 1; }", Typing::Lax), Err("1:14: assigned read-only loop counter: i".to_string()));
+        assert_eq!(validate("rep 3 as i { read i; }", Typing::Lax), Err("1:14: assigned read-only loop counter: i".to_string()));
+        assert_eq!(validate("rep 3 as i { } print i;", Typing::Lax), Err("1:22: undeclared variable: i".to_string()));
+    }
+
+    #[test]
+    fn checks_calls_and_returns() {
+        assert_eq!(validate("print f(1, 2); fn f(a, b) { return a + b; }", Typing::Lax), Ok(()));
+        assert_eq!(validate("fn f(a, b) { return a + b; } print f(1);", Typing::Lax), Err("1:36: wrong number of arguments to f: expected 2, received 1".to_string()));
+        assert_eq!(validate("fn f() { return 0; } print f(1, 2, 3);", Typing::Lax), Err("1:28: wrong number of arguments to f: expected 0, received 3".to_string()));
+        assert_eq!(validate("print g();", Typing::Lax), Err("1:7: undeclared function: g".to_string()));
+        assert_eq!(validate("return 1;", Typing::Lax), Err("1:1: return outside of a function".to_string()));
+        assert_eq!(validate("rep 1 { return 1; }", Typing::Lax), Err("1:9: return outside of a function".to_string()));
+        assert_eq!(validate("fn f() { return 0; } fn f() { return 1; }", Typing::Lax), Err("1:22: redeclared function: f".to_string()));
+    }
+
+    #[test]
+    fn functions_do_not_see_global_variables() {
+        assert_eq!(validate("let x be 1; fn f() { return x; }", Typing::Lax), Err("1:29: undeclared variable: x".to_string()));
+        assert_eq!(validate("rep 1 { fn f() { return 0; } }", Typing::Lax), Err("1:9: function declared outside the global scope: f".to_string()));
+    }
+
+    #[test]
+    fn eat_ends_a_lifetime_in_the_outermost_block() {
+        assert_eq!(validate("let x be 1; eat x; let x be 2; print x;", Typing::Lax), Ok(()));
+        assert_eq!(validate("let x be 1; eat x; print x;", Typing::Lax), Err("1:26: undeclared variable: x".to_string()));
+        assert_eq!(validate("let x be 1; let x be 2;", Typing::Lax), Err("1:13: redeclared variable: x".to_string()));
+        assert_eq!(validate("eat x;", Typing::Lax), Err("1:1: undeclared variable: x".to_string()));
+        assert_eq!(validate("let x be 1; rep 2 { eat x; }", Typing::Lax), Err("1:21: ate variable outside the outermost block: x".to_string()));
+    }
+
+    #[test]
+    fn arrays_are_only_used_through_an_index() {
+        assert_eq!(validate("let a be [3]; set a[0] to 1; print a[a[0]];", Typing::Lax), Ok(()));
+        assert_eq!(validate("let x be 1; print x[0];", Typing::Lax), Err("1:19: indexed a variable that is not an array: x".to_string()));
+        assert_eq!(validate("let x be 1; set x[0] to 1;", Typing::Lax), Err("1:13: indexed a variable that is not an array: x".to_string()));
+        assert_eq!(validate("let a be [3]; print a;", Typing::Lax), Err("1:21: used an array without an index: a".to_string()));
+        assert_eq!(validate("let a be [3]; set a to 1;", Typing::Lax), Err("1:15: used an array without an index: a".to_string()));
+        assert_eq!(validate("let a be [3]; read a;", Typing::Lax), Err("1:15: used an array without an index: a".to_string()));
+    }
+
+    #[test]
+    fn array_sizes_are_bounded() {
+        assert_eq!(validate("let a be [65536];", Typing::Lax), Ok(()));
+        assert_eq!(validate("let a be [0];", Typing::Lax), Err("1:1: array size must be between 1 and 65536: a has size 0".to_string()));
+        assert_eq!(validate("let a be [65537];", Typing::Lax), Err("1:1: array size must be between 1 and 65536: a has size 65537".to_string()));
+    }
+
+    #[test]
+    fn strict_typing_keeps_booleans_apart_from_integers() {
+        let source = "let x be 1; let y be 2; print (x = 2) + y;";
+        assert_eq!(validate(source, Typing::Lax), Ok(()));
+        assert_eq!(validate(source, Typing::Strict), Err("1:31: `+` expects int, received bool".to_string()));
+
+        assert_eq!(validate("print !1;", Typing::Strict), Err("1:8: `!` expects bool, received int".to_string()));
+        assert_eq!(validate("print 1 & 1;", Typing::Strict), Err("1:7: `&` expects bool, received int".to_string()));
+        assert_eq!(validate("print !(1 = 1) & 2 < 3;", Typing::Strict), Ok(()));
+    }
+
+    #[test]
+    fn strict_rep_counts_may_be_integers_or_booleans() {
+        assert_eq!(validate("let x be 3; rep x { print x; }", Typing::Strict), Ok(()));
+        assert_eq!(validate("let x be 3; rep x > 2 { print x; } else { print 0; }", Typing::Strict), Ok(()));
+    }
+
+    #[test]
+    fn strict_statements_report_mismatches() {
+        assert_eq!(validate("let x be 1; set x to 1 = 1;", Typing::Strict), Err("1:22: `set` statement expects int, received bool".to_string()));
+        assert_eq!(validate("let b be 1 = 1; read b;", Typing::Strict), Err("1:17: `read` statement expects int, received bool".to_string()));
+        assert_eq!(validate("let x be 1; while x { set x to 0; }", Typing::Strict), Err("1:19: `while` statement expects bool, received int".to_string()));
+        assert_eq!(validate("fn f(a) { return a; } print f(1 < 2);", Typing::Strict), Err("1:31: call to f expects int, received bool".to_string()));
+        assert_eq!(validate("fn f(a) { return a = 0; }", Typing::Strict), Err("1:18: `return` statement expects int, received bool".to_string()));
+    }
+}
@@ -1,13 +1,13 @@
 use std::{fmt::Display, borrow::Cow};
 
 #[derive(Debug, Clone, PartialEq,)]
-pub enum Token {
+pub enum Token<'src> {
     /* Arithmetic Operators */
     Add,    // +
     Sub,    // -
     Mul,    // *
     Div,    // /
-    // Mod,      // %
+    Mod,    // %
 
     /* Logical Operators */
     Is,     // ?
@@ -15,77 +15,240 @@ pub enum Token {
     And,    // &
     Or,     // |
 
+    /* Bitwise Operators */
+    BitAnd, // .&
+    BitOr,  // .|
+    Xor,    // ^
+    BitNot, // ~
+    Shl,    // <<
+    Shr,    // >>
+
     /* Comparison Operators */
     Equals,     // =
     Less,       // <
     Greater,    // >
+    LessEquals,     // <=
+    GreaterEquals,  // >=
+    NotEquals,      // !=
 
     /* Keywords */
     Let, Be,    // Declare
     Set, To,    // Assign
-    Rep,        // Loop
+    Rep, As, Else, // Loop
+    While,      // Conditional loop
+    Break, Continue, // Jump
+    Fn, Return, // Function
     Print,      // Print
-    // Eat      // Drop
+    Read,       // Input
+    Eat,        // Drop
 
     /* Control & Ordering */
     LParen,     // (
     RParen,     // )
     LBrace,     // {
     RBrace,     // }
+    LBracket,   // [
+    RBracket,   // ]
     Semicolon,  // ;
+    Comma,      // ,
 
     /* Data */
-    Constant(i32),      // ex. 20
-    Variable(String),   // ex. x
-}
+    Constant(i64),          // ex. 20, wide enough to hold 2147483648 until it is negated
+    Variable(&'src str),    // ex. x
+    Str(Cow<'src, str>),    // ex. "n = ", with escapes already resolved
 
-impl From<i32> for Token {
-    fn from(value: i32) -> Self {
-        Self::Constant(value)
-    }
+    /* Recovery */
+    Error,      // stands in for an invalid character or literal
 }
 
-impl From<String> for Token {
-    fn from(value: String) -> Self {
-        Self::Variable(value)
+impl From<i64> for Token<'_> {
+    fn from(value: i64) -> Self {
+        Self::Constant(value)
     }
 }
 
-impl<'a> From<&'a str> for Token {
-    fn from(other: &'a str) -> Self {
-        Self::Variable(other.to_string())
+impl<'src> From<&'src str> for Token<'src> {
+    fn from(other: &'src str) -> Self {
+        Self::Variable(other)
     }
 }
 
-impl Display for Token {
+impl Display for Token<'_> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let output: Cow<str> = match self {
             Token::Add => "add".into(),
             Token::Sub => "sub".into(),
             Token::Mul => "mul".into(),
             Token::Div => "div".into(),
+            Token::Mod => "mod".into(),
             Token::Is => "is".into(),
             Token::Not => "not".into(),
             Token::And => "and".into(),
             Token::Or => "or".into(),
+            Token::BitAnd => "bit_and".into(),
+            Token::BitOr => "bit_or".into(),
+            Token::Xor => "xor".into(),
+            Token::BitNot => "bit_not".into(),
+            Token::Shl => "shl".into(),
+            Token::Shr => "shr".into(),
             Token::Equals => "equals".into(),
             Token::Less => "less".into(),
             Token::Greater => "greater".into(),
+            Token::LessEquals => "less_equals".into(),
+            Token::GreaterEquals => "greater_equals".into(),
+            Token::NotEquals => "not_equals".into(),
             Token::Let => "let".into(),
             Token::Be => "be".into(),
             Token::Set => "set".into(),
             Token::To => "to".into(),
             Token::Rep => "rep".into(),
+            Token::As => "as".into(),
+            Token::Else => "else".into(),
+            Token::While => "while".into(),
+            Token::Break => "break".into(),
+            Token::Continue => "continue".into(),
+            Token::Fn => "fn".into(),
+            Token::Return => "return".into(),
             Token::Print => "print".into(),
+            Token::Read => "read".into(),
+            Token::Eat => "eat".into(),
             Token::LParen => "l_paren".into(),
             Token::RParen => "r_paren".into(),
             Token::LBrace => "l_brace".into(),
             Token::RBrace => "r_brace".into(),
+            Token::LBracket => "l_bracket".into(),
+            Token::RBracket => "r_bracket".into(),
             Token::Semicolon => "semicolon".into(),
+            Token::Comma => "comma".into(),
             Token::Constant(i) => Cow::Owned(format!("constant({})", i)),
             Token::Variable(s) => Cow::Owned(format!("variable({})", s)),
+            Token::Str(s) => Cow::Owned(format!("string({:?})", s)),
+            Token::Error => "error".into(),
         };
 
         write!(f, "<{output}>")
     }
-}
\ No newline at end of file
+}
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Add, Sub, Mul, Div, Mod,
+    Is, Not, And, Or,
+    BitAnd, BitOr, Xor, BitNot, Shl, Shr,
+    Equals, Less, Greater, LessEquals, GreaterEquals, NotEquals,
+    Let, Be, Set, To, Rep, As, Else, While, Break, Continue, Fn, Return, Print, Read, Eat,
+    LParen, RParen, LBrace, RBrace, LBracket, RBracket, Semicolon, Comma,
+    Constant, Variable, Str,
+    Error,
+}
+
+impl Token<'_> {
+    pub fn kind(&self) -> TokenKind {
+        match self {
+            Token::Add => TokenKind::Add,
+            Token::Sub => TokenKind::Sub,
+            Token::Mul => TokenKind::Mul,
+            Token::Div => TokenKind::Div,
+            Token::Mod => TokenKind::Mod,
+            Token::Is => TokenKind::Is,
+            Token::Not => TokenKind::Not,
+            Token::And => TokenKind::And,
+            Token::Or => TokenKind::Or,
+            Token::BitAnd => TokenKind::BitAnd,
+            Token::BitOr => TokenKind::BitOr,
+            Token::Xor => TokenKind::Xor,
+            Token::BitNot => TokenKind::BitNot,
+            Token::Shl => TokenKind::Shl,
+            Token::Shr => TokenKind::Shr,
+            Token::Equals => TokenKind::Equals,
+            Token::Less => TokenKind::Less,
+            Token::Greater => TokenKind::Greater,
+            Token::LessEquals => TokenKind::LessEquals,
+            Token::GreaterEquals => TokenKind::GreaterEquals,
+            Token::NotEquals => TokenKind::NotEquals,
+            Token::Let => TokenKind::Let,
+            Token::Be => TokenKind::Be,
+            Token::Set => TokenKind::Set,
+            Token::To => TokenKind::To,
+            Token::Rep => TokenKind::Rep,
+            Token::As => TokenKind::As,
+            Token::Else => TokenKind::Else,
+            Token::While => TokenKind::While,
+            Token::Break => TokenKind::Break,
+            Token::Continue => TokenKind::Continue,
+            Token::Fn => TokenKind::Fn,
+            Token::Return => TokenKind::Return,
+            Token::Print => TokenKind::Print,
+            Token::Read => TokenKind::Read,
+            Token::Eat => TokenKind::Eat,
+            Token::LParen => TokenKind::LParen,
+            Token::RParen => TokenKind::RParen,
+            Token::LBrace => TokenKind::LBrace,
+            Token::RBrace => TokenKind::RBrace,
+            Token::LBracket => TokenKind::LBracket,
+            Token::RBracket => TokenKind::RBracket,
+            Token::Semicolon => TokenKind::Semicolon,
+            Token::Comma => TokenKind::Comma,
+            Token::Constant(_) => TokenKind::Constant,
+            Token::Variable(_) => TokenKind::Variable,
+            Token::Str(_) => TokenKind::Str,
+            Token::Error => TokenKind::Error,
+        }
+    }
+}
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let output = match self {
+            TokenKind::Add => "'+'",
+            TokenKind::Sub => "'-'",
+            TokenKind::Mul => "'*'",
+            TokenKind::Div => "'/'",
+            TokenKind::Mod => "'%'",
+            TokenKind::Is => "'?'",
+            TokenKind::Not => "'!'",
+            TokenKind::And => "'&'",
+            TokenKind::Or => "'|'",
+            TokenKind::BitAnd => "'.&'",
+            TokenKind::BitOr => "'.|'",
+            TokenKind::Xor => "'^'",
+            TokenKind::BitNot => "'~'",
+            TokenKind::Shl => "'<<'",
+            TokenKind::Shr => "'>>'",
+            TokenKind::Equals => "'='",
+            TokenKind::Less => "'<'",
+            TokenKind::Greater => "'>'",
+            TokenKind::LessEquals => "'<='",
+            TokenKind::GreaterEquals => "'>='",
+            TokenKind::NotEquals => "'!='",
+            TokenKind::Let => "'let'",
+            TokenKind::Be => "'be'",
+            TokenKind::Set => "'set'",
+            TokenKind::To => "'to'",
+            TokenKind::Rep => "'rep'",
+            TokenKind::As => "'as'",
+            TokenKind::Else => "'else'",
+            TokenKind::While => "'while'",
+            TokenKind::Break => "'break'",
+            TokenKind::Continue => "'continue'",
+            TokenKind::Fn => "'fn'",
+            TokenKind::Return => "'return'",
+            TokenKind::Print => "'print'",
+            TokenKind::Read => "'read'",
+            TokenKind::Eat => "'eat'",
+            TokenKind::LParen => "'('",
+            TokenKind::RParen => "')'",
+            TokenKind::LBrace => "'{'",
+            TokenKind::RBrace => "'}'",
+            TokenKind::LBracket => "'['",
+            TokenKind::RBracket => "']'",
+            TokenKind::Semicolon => "';'",
+            TokenKind::Comma => "','",
+            TokenKind::Constant => "constant",
+            TokenKind::Variable => "identifier",
+            TokenKind::Str => "string",
+            TokenKind::Error => "invalid token",
+        };
+
+        write!(f, "{output}")
+    }
+}